@@ -1,15 +1,17 @@
 use cabocha::parser::Parser;
 
 fn main() {
-  let mut parser = Parser::new("");
+  let mut parser = Parser::new("").unwrap();
   let text = "一郎は二郎が描いた絵を三郎に贈った。";
 
-  println!("{}", parser.parse_to_str(text));
+  println!("{}", parser.parse_to_str(text).unwrap());
 
-  let tree = parser.parse_to_tree(text);
+  let tree = parser.parse_to_tree(text).unwrap();
   println!(
     "{}",
-    tree.to_string(cabocha::consts::CABOCHA_FORMAT::LATTICE)
+    tree
+      .to_string(cabocha::consts::CABOCHA_FORMAT::LATTICE)
+      .unwrap()
   );
 
   for (idx, chunk) in tree.chunk_iter().enumerate() {
@@ -21,11 +23,12 @@ fn main() {
       chunk.func_pos(),
       chunk.score(),
     );
-    let token_pos = chunk.token_pos();
-    for i in token_pos..(token_pos + chunk.token_size()) {
-      if let Some(token) = tree.token(i) {
-        println!("{}\t{}", token.surface(), token.feature());
-      }
+    for token in chunk.tokens() {
+      println!(
+        "{}\t{}",
+        token.surface_str().unwrap(),
+        token.feature_str().unwrap()
+      );
     }
   }
 }
@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+
+use rayon::prelude::*;
+use serde_json::json;
+
+use cabocha::consts::*;
+use cabocha::document::split_sentences;
+use cabocha::owned::OwnedTree;
+use cabocha::parser::{Parser, ParserBuilder};
+use cabocha::pool::ParserPool;
+
+const USAGE: &str = "Usage: cabocha-rs [OPTIONS] [FILE...]
+
+Parse sentences from FILEs (or stdin, also given as -) one line at a time
+and print one JSON object per sentence.
+
+Options:
+  -m, --model PATH         parser model
+      --chunker-model PATH chunker model
+      --ne-model PATH      NE model
+  -d, --dicdir PATH        MeCab dictionary directory
+  -u, --userdic PATH       MeCab user dictionary
+  -r, --rcfile PATH        cabocha rc file
+  -I, --input-layer N      0: raw sentence, 1: POS, 2: chunk, 3: selection, 4: dependency;
+                           above 0, input is lattice blocks terminated by EOS
+  -O, --output-layer N     0: raw sentence, 1: POS, 2: chunk, 3: selection, 4: dependency
+  -n, --ne N               0: none, 1: with constraint, 2: without constraint
+  -f, --format FORMAT      json (default), tree, lattice, tree-lattice, xml, conll, conllu
+  -s, --split              split lines into sentences at 。！？ outside brackets
+  -j, --jobs N             number of parsers run in parallel (default 1)
+  -h, --help               print this help";
+
+const BATCH_SIZE: usize = 1024;
+
+enum Format {
+  Json,
+  Conllu,
+  Cabocha(CABOCHA_FORMAT),
+}
+
+struct Options {
+  builder: ParserBuilder,
+  format: Format,
+  jobs: usize,
+  split: bool,
+  /// Set when the input is lattice blocks rather than raw sentences.
+  lattice_layer: Option<CABOCHA_INPUT>,
+  files: Vec<String>,
+}
+
+fn parse_number(flag: &str, value: &str, max: usize) -> Result<usize, String> {
+  match value.parse::<usize>() {
+    Ok(n) if n <= max => Ok(n),
+    _ => Err(format!(
+      "{} expects a number from 0 to {}, got {:?}",
+      flag, max, value
+    )),
+  }
+}
+
+/// `Ok(None)` means `--help` was given.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+  let mut options = Options {
+    builder: Parser::builder(),
+    format: Format::Json,
+    jobs: 1,
+    split: false,
+    lattice_layer: None,
+    files: Vec::new(),
+  };
+  while let Some(arg) = args.next() {
+    if arg == "--" {
+      options.files.extend(args.by_ref());
+      break;
+    }
+    if arg == "-" || !arg.starts_with('-') {
+      options.files.push(arg);
+      continue;
+    }
+    let (flag, inline) = match arg.find('=') {
+      Some(pos) if arg.starts_with("--") => (&arg[..pos], Some(arg[pos + 1..].to_string())),
+      _ => (arg.as_str(), None),
+    };
+    let mut value = || {
+      inline
+        .clone()
+        .or_else(|| args.next())
+        .ok_or_else(|| format!("{} requires a value", flag))
+    };
+    let builder = options.builder.clone();
+    options.builder = match flag {
+      "-m" | "--model" => builder.parser_model(value()?),
+      "--chunker-model" => builder.chunker_model(value()?),
+      "--ne-model" => builder.ne_model(value()?),
+      "-d" | "--dicdir" => builder.mecab_dicdir(value()?),
+      "-u" | "--userdic" => builder.mecab_userdic(value()?),
+      "-r" | "--rcfile" => builder.rcfile(value()?),
+      "-I" | "--input-layer" => {
+        let layer = match parse_number(flag, &value()?, 4)? {
+          0 => CABOCHA_INPUT::RAW_SENTENCE,
+          1 => CABOCHA_INPUT::POS,
+          2 => CABOCHA_INPUT::CHUNK,
+          3 => CABOCHA_INPUT::SELECTION,
+          _ => CABOCHA_INPUT::DEP,
+        };
+        options.lattice_layer = Some(layer).filter(|&layer| layer != CABOCHA_INPUT::RAW_SENTENCE);
+        builder.input_layer(layer)
+      }
+      "-O" | "--output-layer" => builder.output_layer(match parse_number(flag, &value()?, 4)? {
+        0 => CABOCHA_OUTPUT::RAW_SENTENCE,
+        1 => CABOCHA_OUTPUT::POS,
+        2 => CABOCHA_OUTPUT::CHUNK,
+        3 => CABOCHA_OUTPUT::SELECTION,
+        _ => CABOCHA_OUTPUT::DEP,
+      }),
+      "-n" | "--ne" => builder.ne(match parse_number(flag, &value()?, 2)? {
+        0 => CABOCHA_NE_MODE::NONE,
+        1 => CABOCHA_NE_MODE::CONSTRAINT,
+        _ => CABOCHA_NE_MODE::NO_CONSTRAINT,
+      }),
+      "-f" | "--format" => {
+        let format = value()?;
+        options.format = match format.as_str() {
+          "json" => Format::Json,
+          "conllu" => Format::Conllu,
+          "tree" => Format::Cabocha(CABOCHA_FORMAT::TREE),
+          "lattice" => Format::Cabocha(CABOCHA_FORMAT::LATTICE),
+          "tree-lattice" => Format::Cabocha(CABOCHA_FORMAT::TREE_LATTICE),
+          "xml" => Format::Cabocha(CABOCHA_FORMAT::XML),
+          "conll" => Format::Cabocha(CABOCHA_FORMAT::CONLL),
+          _ => return Err(format!("unknown format {:?}", format)),
+        };
+        builder
+      }
+      "-j" | "--jobs" => {
+        let jobs = value()?;
+        options.jobs = match jobs.parse::<usize>() {
+          Ok(jobs) if jobs > 0 => jobs,
+          _ => {
+            return Err(format!(
+              "{} expects a positive number, got {:?}",
+              flag, jobs
+            ))
+          }
+        };
+        builder
+      }
+      "-s" | "--split" => {
+        options.split = true;
+        builder
+      }
+      "-h" | "--help" => return Ok(None),
+      _ => return Err(format!("unknown option {}", flag)),
+    };
+  }
+  if options.files.is_empty() {
+    options.files.push(String::from("-"));
+  }
+  Ok(Some(options))
+}
+
+/// Sentences of one input: one per line, split further with `--split`,
+/// or `EOS`-terminated lattice blocks when the input layer is above raw sentences.
+struct Records {
+  reader: Box<dyn BufRead>,
+  lattice: bool,
+  split: bool,
+  pending: VecDeque<String>,
+}
+
+impl Iterator for Records {
+  type Item = io::Result<String>;
+
+  fn next(&mut self) -> Option<io::Result<String>> {
+    let mut block = String::new();
+    loop {
+      if let Some(record) = self.pending.pop_front() {
+        return Some(Ok(record));
+      }
+      let mut line = String::new();
+      match self.reader.read_line(&mut line) {
+        Ok(0) if block.is_empty() => return None,
+        Ok(0) => return Some(Ok(block)),
+        Ok(_) => {}
+        Err(err) => return Some(Err(err)),
+      }
+      let line = line.trim_end_matches(['\n', '\r']);
+      if self.lattice {
+        block.push_str(line);
+        block.push('\n');
+        if line == "EOS" {
+          return Some(Ok(block));
+        }
+      } else if self.split {
+        self.pending = split_sentences(line)
+          .into_iter()
+          .map(|range| line[range].to_string())
+          .collect();
+      } else if !line.trim().is_empty() {
+        return Some(Ok(line.to_string()));
+      }
+    }
+  }
+}
+
+fn open(path: &str) -> io::Result<Box<dyn BufRead>> {
+  if path == "-" {
+    Ok(Box::new(BufReader::new(io::stdin())))
+  } else {
+    Ok(Box::new(BufReader::new(File::open(path)?)))
+  }
+}
+
+/// Print one parse result; returns whether the sentence parsed.
+fn write_result<W: Write>(
+  out: &mut W,
+  format: &Format,
+  sentence: &str,
+  result: cabocha::Result<OwnedTree>,
+) -> io::Result<bool> {
+  let written = result.and_then(|tree| match format {
+    Format::Json => {
+      serde_json::to_writer(&mut *out, &tree).map_err(io::Error::from)?;
+      writeln!(out)?;
+      Ok(())
+    }
+    Format::Conllu => {
+      out.write_all(tree.to_conllu()?.as_bytes())?;
+      Ok(())
+    }
+    Format::Cabocha(format) => tree.write(out, *format),
+  });
+  match written {
+    Ok(()) => Ok(true),
+    Err(cabocha::Error::Io(err)) => Err(err),
+    Err(err) => {
+      if let Format::Json = format {
+        writeln!(
+          out,
+          "{}",
+          json!({ "sentence": sentence, "error": err.to_string() })
+        )?;
+      }
+      eprintln!("cabocha-rs: {:?}: {}", sentence, err);
+      Ok(false)
+    }
+  }
+}
+
+fn with_path(path: &str, err: io::Error) -> io::Error {
+  io::Error::new(err.kind(), format!("{}: {}", path, err))
+}
+
+fn run<W: Write>(options: Options, out: &mut W) -> io::Result<bool> {
+  let pool = ParserPool::new(&options.builder, options.jobs)
+    .map_err(|err| io::Error::other(err.to_string()))?;
+  let threads = rayon::ThreadPoolBuilder::new()
+    .num_threads(options.jobs)
+    .build()
+    .map_err(|err| io::Error::other(err.to_string()))?;
+  let mut success = true;
+  for path in options.files.iter() {
+    let mut records = Records {
+      reader: open(path).map_err(|err| with_path(path, err))?,
+      lattice: options.lattice_layer.is_some(),
+      split: options.split,
+      pending: VecDeque::new(),
+    };
+    loop {
+      let batch = records
+        .by_ref()
+        .take(BATCH_SIZE)
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(|err| with_path(path, err))?;
+      if batch.is_empty() {
+        break;
+      }
+      let results = threads.install(|| match options.lattice_layer {
+        Some(layer) => pool.par_parse_lattice(batch.par_iter(), layer),
+        None => pool.par_parse(batch.par_iter()),
+      });
+      for (sentence, result) in batch.iter().zip(results) {
+        success &= write_result(out, &options.format, sentence, result)?;
+      }
+      out.flush()?;
+    }
+  }
+  Ok(success)
+}
+
+fn main() {
+  let options = match parse_args(env::args().skip(1)) {
+    Ok(Some(options)) => options,
+    Ok(None) => {
+      println!("{}", USAGE);
+      return;
+    }
+    Err(message) => {
+      eprintln!("cabocha-rs: {}\n\n{}", message, USAGE);
+      process::exit(2);
+    }
+  };
+  let stdout = io::stdout();
+  let mut out = BufWriter::new(stdout.lock());
+  match run(options, &mut out) {
+    Ok(true) => {}
+    Ok(false) => process::exit(1),
+    // a closed pipe (e.g. `| head`) is not an error
+    Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+    Err(err) => {
+      eprintln!("cabocha-rs: {}", err);
+      process::exit(1);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Result<Option<Options>, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+  }
+
+  fn records(input: &'static str, lattice: bool, split: bool) -> Vec<String> {
+    Records {
+      reader: Box::new(input.as_bytes()),
+      lattice,
+      split,
+      pending: VecDeque::new(),
+    }
+    .collect::<io::Result<Vec<_>>>()
+    .unwrap()
+  }
+
+  #[test]
+  fn test_parse_args() {
+    let options = args(&["-j", "4", "--format=conllu", "-I", "1", "a.txt", "--", "-b"])
+      .unwrap()
+      .unwrap();
+    assert_eq!(4, options.jobs);
+    assert!(matches!(options.format, Format::Conllu));
+    assert_eq!(Some(CABOCHA_INPUT::POS), options.lattice_layer);
+    assert_eq!(vec!["a.txt", "-b"], options.files);
+
+    let options = args(&["-I", "0"]).unwrap().unwrap();
+    assert_eq!(None, options.lattice_layer);
+    let options = args(&[]).unwrap().unwrap();
+    assert_eq!(1, options.jobs);
+    assert!(matches!(options.format, Format::Json));
+    assert_eq!(vec!["-"], options.files);
+
+    assert!(args(&["--help"]).unwrap().is_none());
+    assert!(args(&["-j", "0"]).is_err());
+    assert!(args(&["-I", "5"]).is_err());
+    assert!(args(&["--format", "yaml"]).is_err());
+    assert!(args(&["--model"]).is_err());
+    assert!(args(&["--bogus"]).is_err());
+  }
+
+  #[test]
+  fn test_records() {
+    assert_eq!(
+      vec!["猫が鳴いた。犬も。", "鳥"],
+      records("猫が鳴いた。犬も。\r\n\n鳥", false, false)
+    );
+    assert_eq!(
+      vec!["猫が鳴いた。", "犬も。", "鳥"],
+      records("猫が鳴いた。犬も。\r\n\n鳥", false, true)
+    );
+    assert_eq!(
+      vec!["猫\t名詞\nEOS\n", "鳥\t名詞\n"],
+      records("猫\t名詞\nEOS\n鳥\t名詞\n", true, false)
+    );
+  }
+
+  #[test]
+  fn test_run_lattice_input() {
+    let path = env::temp_dir().join(format!("cabocha-rs-test-{}.lattice", process::id()));
+    let block = "猫\t名詞,一般,*,*,*,*,猫,ネコ,ネコ
+が\t助詞,格助詞,一般,*,*,*,が,ガ,ガ
+鳴い\t動詞,自立,*,*,五段・カ行イ音便,連用タ接続,鳴く,ナイ,ナイ
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ
+EOS
+";
+    std::fs::write(&path, format!("{}{}", block, block)).unwrap();
+    let options = args(&[
+      "-I",
+      "1",
+      "-f",
+      "lattice",
+      "-j",
+      "2",
+      path.to_str().unwrap(),
+    ])
+    .unwrap()
+    .unwrap();
+    let mut out = Vec::new();
+    let success = run(options, &mut out);
+    std::fs::remove_file(&path).unwrap();
+    assert!(success.unwrap());
+    let output = String::from_utf8(out).unwrap();
+    assert_eq!(2, output.matches("* 0 1D 0/1 ").count());
+    assert_eq!(2, output.matches("\n* 1 -1D 0/1 ").count());
+    assert_eq!(2, output.matches("鳴い\t動詞,自立,").count());
+    assert!(output.ends_with("EOS\n"));
+  }
+}
@@ -1,15 +1,38 @@
+use std::ops::Range;
+
+use super::chunk_features::*;
+use super::error::*;
 use super::sys::*;
+use super::token::*;
 use super::tree::*;
 use super::utils::*;
 
-#[derive(Debug, Clone)]
-pub struct Chunk {
+#[derive(Debug, Clone, Copy)]
+pub struct Chunk<'tree> {
+  tree: &'tree Tree,
+  index: usize,
   self_ptr: *const cabocha_chunk_t,
 }
 
-impl Chunk {
-  pub fn new(raw_ptr: *const cabocha_chunk_t) -> Chunk {
-    Chunk { self_ptr: raw_ptr }
+impl<'tree> Chunk<'tree> {
+  pub(crate) fn new(
+    tree: &'tree Tree,
+    index: usize,
+    raw_ptr: *const cabocha_chunk_t,
+  ) -> Chunk<'tree> {
+    Chunk {
+      tree,
+      index,
+      self_ptr: raw_ptr,
+    }
+  }
+
+  pub fn tree(&self) -> &'tree Tree {
+    self.tree
+  }
+
+  pub fn index(&self) -> usize {
+    self.index
   }
 
   pub fn link(&self) -> i32 {
@@ -36,20 +59,158 @@ impl Chunk {
     unsafe { (*self.self_ptr).score }
   }
 
-  pub fn feature_list(&self) -> Vec<String> {
+  pub fn feature_list(&self) -> Result<Vec<String>> {
     unsafe {
       let chunk = &*self.self_ptr;
       ptr_to_vec_string(chunk.feature_list, chunk.feature_list_size as usize)
     }
   }
 
-  pub fn additional_info(&self) -> String {
-    unsafe { ptr_to_string((*self.self_ptr).additional_info) }
+  pub fn feature_iter(&self) -> FeatureIter<'tree> {
+    unsafe {
+      let chunk = &*self.self_ptr;
+      FeatureIter::new(chunk.feature_list, chunk.feature_list_size as usize)
+    }
+  }
+
+  pub fn features(&self) -> Result<ChunkFeatures> {
+    let features = self.feature_iter().collect::<Result<Vec<_>>>()?;
+    Ok(ChunkFeatures::parse(&features))
+  }
+
+  pub fn additional_info(&self) -> Result<String> {
+    self.additional_info_str().map(str::to_string)
+  }
+
+  pub fn additional_info_str(&self) -> Result<&'tree str> {
+    unsafe { ptr_to_str((*self.self_ptr).additional_info) }
   }
 
   pub fn feature_list_size(&self) -> u16 {
     unsafe { (*self.self_ptr).feature_list_size }
   }
+
+  pub fn tokens(&self) -> Vec<Token<'tree>> {
+    let token_pos = self.token_pos();
+    (token_pos..(token_pos + self.token_size()))
+      .filter_map(|index| self.tree.token(index))
+      .collect()
+  }
+
+  pub fn head_token(&self) -> Option<Token<'tree>> {
+    self.tree.token(self.token_pos() + self.head_pos())
+  }
+
+  pub fn func_token(&self) -> Option<Token<'tree>> {
+    self.tree.token(self.token_pos() + self.func_pos())
+  }
+
+  pub fn surface(&self) -> Result<String> {
+    let mut surface = String::new();
+    for token in self.tokens() {
+      surface.push_str(token.surface_str()?);
+    }
+    Ok(surface)
+  }
+
+  /// Aligns the surfaces of all tokens up to the end of this chunk on each call;
+  /// use `Tree::token_byte_spans` when spans of many chunks are needed.
+  pub fn span(&self) -> Result<Range<usize>> {
+    let end = self.token_pos() + self.token_size();
+    if self.token_size() == 0 || end > self.tree.token_size() {
+      return Err(Error::Alignment(self.token_pos()));
+    }
+    let spans = self.tree.token_byte_spans_until(end)?;
+    Ok(spans[self.token_pos()].start..spans[end - 1].end)
+  }
+
+  pub fn char_span(&self) -> Result<Range<usize>> {
+    let sentence = self.tree.sentence_str()?;
+    Ok(to_char_span(sentence, &self.span()?))
+  }
+
+  pub fn head(&self) -> Option<Chunk<'tree>> {
+    let link = self.link();
+    if link < 0 {
+      None
+    } else {
+      self.tree.chunk(link as usize)
+    }
+  }
+
+  pub fn dependents(&self) -> Vec<Chunk<'tree>> {
+    self
+      .tree
+      .chunk_iter()
+      .filter(|chunk| chunk.link() == self.index as i32)
+      .collect()
+  }
+
+  pub fn ancestors(&self) -> Ancestors<'tree> {
+    Ancestors::new(*self)
+  }
+
+  pub fn descendants(&self) -> Vec<Chunk<'tree>> {
+    let mut descendants = Vec::new();
+    let mut visited = vec![false; self.tree.chunk_size()];
+    let mut stack = vec![*self];
+    if let Some(flag) = visited.get_mut(self.index) {
+      *flag = true;
+    }
+    while let Some(chunk) = stack.pop() {
+      for dependent in chunk.dependents() {
+        if !visited[dependent.index] {
+          visited[dependent.index] = true;
+          descendants.push(dependent);
+          stack.push(dependent);
+        }
+      }
+    }
+    descendants.sort_by_key(|chunk| chunk.index);
+    descendants
+  }
+
+  pub fn subtree(&self) -> Vec<Chunk<'tree>> {
+    let mut subtree = self.descendants();
+    let pos = subtree
+      .iter()
+      .position(|chunk| chunk.index > self.index)
+      .unwrap_or(subtree.len());
+    subtree.insert(pos, *self);
+    subtree
+  }
+
+  pub fn depth(&self) -> usize {
+    self.ancestors().count()
+  }
+}
+
+pub struct Ancestors<'tree> {
+  chunk: Option<Chunk<'tree>>,
+  remaining: usize,
+}
+
+impl<'tree> Iterator for Ancestors<'tree> {
+  type Item = Chunk<'tree>;
+
+  fn next(&mut self) -> Option<Chunk<'tree>> {
+    // malformed links may form a cycle, so never walk more than chunk_size steps
+    if self.remaining == 0 {
+      return None;
+    }
+    self.remaining -= 1;
+    self.chunk = self.chunk.and_then(|chunk| chunk.head());
+    self.chunk
+  }
+}
+
+impl<'tree> Ancestors<'tree> {
+  fn new(chunk: Chunk<'tree>) -> Ancestors<'tree> {
+    Ancestors {
+      chunk: Some(chunk),
+      remaining: chunk.tree.chunk_size(),
+    }
+  }
 }
 
 pub struct ChunkIter<'a> {
@@ -58,9 +219,9 @@ pub struct ChunkIter<'a> {
 }
 
 impl<'a> Iterator for ChunkIter<'a> {
-  type Item = Chunk;
+  type Item = Chunk<'a>;
 
-  fn next(&mut self) -> Option<Chunk> {
+  fn next(&mut self) -> Option<Chunk<'a>> {
     let chunk = self.tree.chunk(self.pos);
     self.pos += 1;
     chunk
@@ -68,7 +229,80 @@ impl<'a> Iterator for ChunkIter<'a> {
 }
 
 impl<'a> ChunkIter<'a> {
-  pub fn new(tree: &Tree) -> ChunkIter {
+  pub fn new(tree: &'a Tree) -> ChunkIter<'a> {
     ChunkIter { tree, pos: 0 }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Parser;
+
+  fn indices(chunks: Vec<Chunk>) -> Vec<usize> {
+    chunks.iter().map(|chunk| chunk.index()).collect()
+  }
+
+  #[test]
+  fn test_chunk_navigation() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+
+    let root = tree.root().unwrap();
+    assert_eq!(5, root.index());
+    assert!(root.head().is_none());
+    assert_eq!(0, root.depth());
+    assert_eq!(vec![0, 3, 4], indices(root.dependents()));
+    assert_eq!(vec![0, 1, 2, 3, 4], indices(root.descendants()));
+
+    let chunk = tree.chunk(1).unwrap();
+    assert_eq!(2, chunk.head().unwrap().index());
+    assert_eq!(vec![2, 3, 5], indices(chunk.ancestors().collect()));
+    assert_eq!(3, chunk.depth());
+    assert!(chunk.dependents().is_empty());
+
+    let chunk = tree.chunk(3).unwrap();
+    assert_eq!(vec![1, 2], indices(chunk.descendants()));
+    assert_eq!(vec![1, 2, 3], indices(chunk.subtree()));
+
+    assert_eq!(
+      vec![1, 2, 3, 5, 4],
+      indices(tree.path_between(1, 4).unwrap())
+    );
+    assert_eq!(vec![5, 3], indices(tree.path_between(5, 3).unwrap()));
+    assert_eq!(vec![2], indices(tree.path_between(2, 2).unwrap()));
+    assert!(tree.path_between(0, 6).is_none());
+  }
+
+  #[test]
+  fn test_chunk_tokens() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+
+    let chunk = tree.chunk(5).unwrap();
+    assert_eq!("贈った。", chunk.surface().unwrap());
+    assert_eq!(
+      vec![10, 11, 12],
+      chunk
+        .tokens()
+        .iter()
+        .map(|token| token.index())
+        .collect::<Vec<_>>()
+    );
+    assert_eq!("贈っ", chunk.head_token().unwrap().surface_str().unwrap());
+    assert_eq!("た", chunk.func_token().unwrap().surface_str().unwrap());
+
+    let token = tree.token(11).unwrap();
+    assert_eq!(11, token.index());
+    assert_eq!(Some(5), token.chunk_index());
+    assert!(token.chunk().is_none());
+    assert_eq!(
+      Some(5),
+      tree.token(10).unwrap().chunk().map(|chunk| chunk.index())
+    );
+  }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WordFeatures {
+  pub surface: Option<String>,
+  /// POS columns in order, from `P0` upward.
+  pub pos: Vec<String>,
+  pub form: Option<String>,
+}
+
+impl WordFeatures {
+  fn set(&mut self, key: &str, value: &str) -> bool {
+    match key {
+      "S" => self.surface = Some(value.to_string()),
+      "F" => self.form = Some(value.to_string()),
+      _ => match key.strip_prefix('P').and_then(|n| n.parse::<usize>().ok()) {
+        Some(n) => {
+          if self.pos.len() <= n {
+            self.pos.resize(n + 1, String::new());
+          }
+          self.pos[n] = value.to_string();
+        }
+        None => return false,
+      },
+    }
+    true
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChunkFeatures {
+  /// `FHS`, `FHP*`, `FHF`: the head word.
+  pub head: WordFeatures,
+  /// `FFS`, `FFP*`, `FFF`: the function word.
+  pub func: WordFeatures,
+  /// `FLS`, `FLP*`, `FLF`: the leftmost word.
+  pub left: WordFeatures,
+  /// `FRS`, `FRP*`, `FRF`: the rightmost word.
+  pub right: WordFeatures,
+  /// `GPUNC`
+  pub global_punctuation: Option<String>,
+  /// `FPUNC`
+  pub punctuation: Option<String>,
+  /// `FEOS:1`
+  pub eos: bool,
+  /// `A`
+  pub aspect: Option<String>,
+  /// `LF`
+  pub left_func: Option<String>,
+  /// `RL`
+  pub right_left: Option<String>,
+  /// `RH`
+  pub right_head: Option<String>,
+  /// `RF`
+  pub right_func: Option<String>,
+  /// Every other key, sorted by key; each key's values stay in input order.
+  pub other: BTreeMap<String, Vec<String>>,
+}
+
+impl ChunkFeatures {
+  pub fn parse<S: AsRef<str>>(features: &[S]) -> ChunkFeatures {
+    let mut result = ChunkFeatures::default();
+    for feature in features {
+      let feature = feature.as_ref();
+      let (key, value) = match feature.find(':') {
+        Some(pos) => (&feature[..pos], &feature[pos + 1..]),
+        None => (feature, ""),
+      };
+      let known = match key {
+        "GPUNC" => {
+          result.global_punctuation = Some(value.to_string());
+          true
+        }
+        "FPUNC" => {
+          result.punctuation = Some(value.to_string());
+          true
+        }
+        "FEOS" => {
+          result.eos = value == "1";
+          true
+        }
+        "A" => {
+          result.aspect = Some(value.to_string());
+          true
+        }
+        "LF" => {
+          result.left_func = Some(value.to_string());
+          true
+        }
+        "RL" => {
+          result.right_left = Some(value.to_string());
+          true
+        }
+        "RH" => {
+          result.right_head = Some(value.to_string());
+          true
+        }
+        "RF" => {
+          result.right_func = Some(value.to_string());
+          true
+        }
+        _ if key.starts_with('F') => {
+          let word = match key.get(1..2) {
+            Some("H") => Some(&mut result.head),
+            Some("F") => Some(&mut result.func),
+            Some("L") => Some(&mut result.left),
+            Some("R") => Some(&mut result.right),
+            _ => None,
+          };
+          word.is_some_and(|word| word.set(&key[2..], value))
+        }
+        _ => false,
+      };
+      if !known {
+        result
+          .other
+          .entry(key.to_string())
+          .or_insert_with(Vec::new)
+          .push(value.to_string());
+      }
+    }
+    result
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Parser;
+
+  const FEATURES: [&str; 22] = [
+    "GPUNC:。",
+    "FPUNC:。",
+    "FHS:贈っ",
+    "FHP0:動詞",
+    "FHP1:自立",
+    "FHF:連用タ接続",
+    "FFS:た",
+    "FFP0:助動詞",
+    "FFF:基本形",
+    "FLS:贈っ",
+    "FLP0:動詞",
+    "FLP1:自立",
+    "FLF:連用タ接続",
+    "FRS:。",
+    "FRP0:記号",
+    "FRP1:句点",
+    "LF:た",
+    "RL:贈っ",
+    "RH:贈っ",
+    "RF:た",
+    "FEOS:1",
+    "A:基本形",
+  ];
+
+  #[test]
+  fn test_parse() {
+    let features = ChunkFeatures::parse(&FEATURES);
+    assert_eq!(Some("贈っ"), features.head.surface.as_deref());
+    assert_eq!(vec!["動詞", "自立"], features.head.pos);
+    assert_eq!(Some("連用タ接続"), features.head.form.as_deref());
+    assert_eq!(Some("た"), features.func.surface.as_deref());
+    assert_eq!(vec!["助動詞"], features.func.pos);
+    assert_eq!(Some("基本形"), features.func.form.as_deref());
+    assert_eq!(Some("贈っ"), features.left.surface.as_deref());
+    assert_eq!(Some("。"), features.right.surface.as_deref());
+    assert_eq!(vec!["記号", "句点"], features.right.pos);
+    assert_eq!(None, features.right.form);
+    assert_eq!(Some("。"), features.global_punctuation.as_deref());
+    assert_eq!(Some("。"), features.punctuation.as_deref());
+    assert!(features.eos);
+    assert_eq!(Some("基本形"), features.aspect.as_deref());
+    assert_eq!(Some("た"), features.left_func.as_deref());
+    assert_eq!(Some("贈っ"), features.right_left.as_deref());
+    assert_eq!(Some("贈っ"), features.right_head.as_deref());
+    assert_eq!(Some("た"), features.right_func.as_deref());
+    assert!(features.other.is_empty());
+  }
+
+  #[test]
+  fn test_parse_unknown_keys() {
+    let features = ChunkFeatures::parse(&["GCASE:ガ格", "GCASE:ヲ格", "FHX:?", "F格:?", "NOCOLON"]);
+    assert_eq!(
+      Some(&vec![String::from("ガ格"), String::from("ヲ格")]),
+      features.other.get("GCASE")
+    );
+    assert_eq!(Some(&vec![String::from("?")]), features.other.get("FHX"));
+    assert_eq!(Some(&vec![String::from("?")]), features.other.get("F格"));
+    assert_eq!(Some(&vec![String::new()]), features.other.get("NOCOLON"));
+    assert!(!features.eos);
+    assert_eq!(WordFeatures::default(), features.head);
+  }
+
+  #[test]
+  fn test_chunk_features() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    let features = tree.chunk(5).unwrap().features().unwrap();
+    assert_eq!(ChunkFeatures::parse(&FEATURES), features);
+
+    let owned = tree.to_owned_tree().unwrap();
+    assert_eq!(features, owned.chunks[5].features());
+  }
+}
@@ -0,0 +1,496 @@
+use std::fmt::Write;
+
+use super::consts::*;
+use super::error::*;
+use super::morph::*;
+use super::owned::*;
+use super::tree::*;
+
+/// Token index of the head of each token, or `None` for the root.
+/// Tokens attach to their chunk's head token, and a chunk's head token
+/// attaches to the head token of the chunk it links to.
+fn token_heads(tree: &OwnedTree) -> Vec<Option<usize>> {
+  let chunk_head = |index: usize| {
+    let chunk = &tree.chunks[index];
+    if chunk.head_pos < chunk.token_size {
+      chunk.token_pos + chunk.head_pos
+    } else {
+      chunk.token_pos
+    }
+  };
+  tree
+    .tokens
+    .iter()
+    .enumerate()
+    .map(|(index, token)| {
+      let chunk = token.chunk?;
+      let head = chunk_head(chunk);
+      if index != head {
+        return Some(head);
+      }
+      let link = tree.chunks[chunk].link;
+      if link >= 0 && (link as usize) < tree.chunks.len() && link as usize != chunk {
+        Some(chunk_head(link as usize))
+      } else {
+        None
+      }
+    })
+    .collect()
+}
+
+/// DEPREL within a chunk, from the dependent's UPOS.
+fn chunk_deprel(upos: &str) -> &'static str {
+  match upos {
+    "ADP" => "case",
+    "AUX" => "aux",
+    "PUNCT" => "punct",
+    "SCONJ" | "PART" => "mark",
+    _ => "dep",
+  }
+}
+
+/// DEPREL between chunks, from the case particle ending the dependent chunk.
+fn case_deprel(particle: Option<&str>) -> &'static str {
+  match particle {
+    Some("が") => "nsubj",
+    Some("を") => "obj",
+    Some(_) => "obl",
+    None => "dep",
+  }
+}
+
+fn or_underscore(value: &str) -> &str {
+  if value.is_empty() {
+    "_"
+  } else {
+    value
+  }
+}
+
+impl OwnedTree {
+  /// Surface of the case particle (格助詞) that is the function word of a chunk.
+  fn case_particle(&self, index: usize) -> Option<&str> {
+    let chunk = &self.chunks[index];
+    if chunk.func_pos >= chunk.token_size {
+      return None;
+    }
+    let token = &self.tokens[chunk.token_pos + chunk.func_pos];
+    let morph = token.morph_features(self.posset.unwrap_or(CABOCHA_POSSET_TYPE::IPA));
+    if morph.pos == "助詞" && morph.pos_detail1.as_deref() == Some("格助詞") {
+      Some(&token.surface)
+    } else {
+      None
+    }
+  }
+
+  /// Write the tree as one CoNLL-U sentence with token-level dependencies.
+  ///
+  /// Trees without chunks get `_` as HEAD and DEPREL. Within a chunk DEPREL follows
+  /// the dependent's UPOS; between chunks it is `nsubj` for a chunk ending in the
+  /// case particle が, `obj` for を, `obl` for other case particles and `dep` otherwise.
+  /// FEATS holds the conjugation (`ConjForm`, `ConjType`); chunk boundaries
+  /// (`BunsetuBILabel`) and NE tags (`NE`) are kept in MISC. `SpaceAfter` is
+  /// omitted when the tokens cannot be aligned with the sentence.
+  pub fn to_conllu(&self) -> Result<String> {
+    let posset = self.posset.unwrap_or(CABOCHA_POSSET_TYPE::IPA);
+    let spans = self.token_byte_spans().ok();
+    let heads = token_heads(self);
+    let mut output = String::new();
+    writeln!(output, "# text = {}", self.sentence).unwrap();
+    for (index, token) in self.tokens.iter().enumerate() {
+      let morph = token.morph_features(posset);
+      let upos = morph.upos();
+      let xpos = std::iter::once(morph.pos.as_str())
+        .chain(morph.pos_details())
+        .collect::<Vec<_>>()
+        .join("-");
+      let (head, deprel) = match (token.chunk, heads[index]) {
+        (None, _) => (String::from("_"), "_"),
+        (Some(_), None) => (String::from("0"), "root"),
+        (Some(chunk), Some(head)) if self.tokens[head].chunk == Some(chunk) => {
+          ((head + 1).to_string(), chunk_deprel(upos))
+        }
+        (Some(chunk), Some(head)) => (
+          (head + 1).to_string(),
+          case_deprel(self.case_particle(chunk)),
+        ),
+      };
+      let mut feats = Vec::new();
+      if let Some(form) = &morph.conjugation_form {
+        feats.push(format!("ConjForm={}", form));
+      }
+      if let Some(conjugation_type) = &morph.conjugation_type {
+        feats.push(format!("ConjType={}", conjugation_type));
+      }
+      let mut misc = Vec::new();
+      if let Some(chunk) = token.chunk {
+        let label = if self.chunks[chunk].token_pos == index {
+          "B"
+        } else {
+          "I"
+        };
+        misc.push(format!("BunsetuBILabel={}", label));
+      }
+      if !token.ne.is_empty() && token.ne != "O" {
+        misc.push(format!("NE={}", token.ne));
+      }
+      if spans.as_ref().is_some_and(|spans| {
+        spans
+          .get(index + 1)
+          .is_some_and(|next| next.start == spans[index].end)
+      }) {
+        misc.push(String::from("SpaceAfter=No"));
+      }
+      writeln!(
+        output,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t_\t{}",
+        index + 1,
+        token.surface,
+        morph.base_form.as_deref().unwrap_or(&token.surface),
+        upos,
+        or_underscore(&xpos),
+        or_underscore(&feats.join("|")),
+        head,
+        deprel,
+        or_underscore(&misc.join("|")),
+      )
+      .unwrap();
+    }
+    output.push('\n');
+    Ok(output)
+  }
+
+  /// Read one CoNLL-U sentence written by `to_conllu`.
+  ///
+  /// Chunks are rebuilt from `BunsetuBILabel`; a chunk links to the chunk
+  /// holding the head of its outward-pointing token. Feature lists are rebuilt
+  /// in IPA column order from XPOS, LEMMA and the `ConjType`/`ConjForm`
+  /// features, so readings are lost. Function words are derived from UPOS as
+  /// in `TreeBuilder`.
+  /// Multiword tokens and empty nodes are skipped. A HEAD out of range or
+  /// pointing at its own token and a sentence starting with
+  /// `BunsetuBILabel=I` are errors.
+  pub fn from_conllu(input: &str) -> Result<OwnedTree> {
+    let mut text = None;
+    let mut tokens = Vec::new();
+    let mut heads = Vec::new();
+    let mut labels = Vec::new();
+    let mut space_after = Vec::new();
+    let mut numbers = Vec::new();
+    let mut lines = input.lines().enumerate();
+    for (number, line) in lines.by_ref() {
+      let line = line.trim_end_matches('\r');
+      if line.is_empty() {
+        if tokens.is_empty() {
+          continue;
+        }
+        break;
+      }
+      if let Some(comment) = line.strip_prefix('#') {
+        if let Some(sentence) = comment.trim_start().strip_prefix("text = ") {
+          text = Some(sentence.to_string());
+        }
+        continue;
+      }
+      let columns = line.split('\t').collect::<Vec<_>>();
+      if columns.len() != 10 {
+        return Err(Error::InvalidInput(format!(
+          "line {}: expected 10 columns, found {}",
+          number + 1,
+          columns.len()
+        )));
+      }
+      if columns[0].contains('-') || columns[0].contains('.') {
+        continue;
+      }
+      let head =
+        match columns[6] {
+          "_" => None,
+          head => Some(head.parse::<usize>().map_err(|_| {
+            Error::InvalidInput(format!("line {}: bad HEAD {:?}", number + 1, head))
+          })?),
+        };
+      let field = |column: &str| match column {
+        "_" => None,
+        value => Some(value.to_string()),
+      };
+      let mut feature_list = columns[4]
+        .split('-')
+        .filter(|_| columns[4] != "_")
+        .map(String::from)
+        .collect::<Vec<_>>();
+      feature_list.resize(4, String::from("*"));
+      let mut conjugation_type = None;
+      let mut conjugation_form = None;
+      let mut ne = String::new();
+      let mut label = None;
+      let mut spaced = true;
+      for feat in columns[5].split('|') {
+        match feat.split_once('=') {
+          Some(("ConjType", value)) => conjugation_type = Some(value.to_string()),
+          Some(("ConjForm", value)) => conjugation_form = Some(value.to_string()),
+          _ => {}
+        }
+      }
+      for misc in columns[9].split('|') {
+        match misc.split_once('=') {
+          Some(("BunsetuBILabel", value)) => label = Some(value == "B"),
+          Some(("NE", value)) => ne = value.to_string(),
+          Some(("SpaceAfter", "No")) => spaced = false,
+          _ => {}
+        }
+      }
+      feature_list.push(conjugation_type.unwrap_or_else(|| String::from("*")));
+      feature_list.push(conjugation_form.unwrap_or_else(|| String::from("*")));
+      feature_list.push(field(columns[2]).unwrap_or_else(|| String::from("*")));
+      tokens.push(OwnedToken {
+        surface: columns[1].to_string(),
+        normalized_surface: columns[1].to_string(),
+        feature: feature_list.join(","),
+        feature_list,
+        ne,
+        additional_info: String::new(),
+        chunk: None,
+      });
+      heads.push(head);
+      labels.push(label);
+      space_after.push(spaced);
+      numbers.push(number + 1);
+    }
+    if lines.any(|(_, line)| !line.trim().is_empty()) {
+      return Err(Error::InvalidInput(String::from(
+        "input holds more than one sentence",
+      )));
+    }
+    for (index, head) in heads.iter().enumerate() {
+      match *head {
+        Some(head) if head > tokens.len() => {
+          return Err(Error::InvalidInput(format!(
+            "line {}: HEAD {} is out of range",
+            numbers[index], head
+          )))
+        }
+        Some(head) if head == index + 1 => {
+          return Err(Error::InvalidInput(format!(
+            "line {}: HEAD {} points at the token itself",
+            numbers[index], head
+          )))
+        }
+        _ => {}
+      }
+    }
+    if labels.first() == Some(&Some(false)) {
+      return Err(Error::InvalidInput(format!(
+        "line {}: the first token has BunsetuBILabel=I",
+        numbers[0]
+      )));
+    }
+
+    let mut chunks: Vec<OwnedChunk> = Vec::new();
+    if labels.iter().any(Option::is_some) {
+      for (index, label) in labels.iter().enumerate() {
+        if index == 0 || *label != Some(false) {
+          chunks.push(OwnedChunk {
+            link: -1,
+            head_pos: 0,
+            func_pos: 0,
+            token_size: 0,
+            token_pos: index,
+            score: 0.0,
+            feature_list: Vec::new(),
+            additional_info: String::new(),
+          });
+        }
+        chunks.last_mut().unwrap().token_size += 1;
+        tokens[index].chunk = Some(chunks.len() - 1);
+      }
+    }
+    for chunk in chunks.iter_mut() {
+      let range = chunk.token_pos..chunk.token_pos + chunk.token_size;
+      if let Some(head) = range.clone().find(|&index| match heads[index] {
+        Some(0) | None => true,
+        Some(head) => !range.contains(&(head - 1)),
+      }) {
+        chunk.head_pos = head - chunk.token_pos;
+        chunk.link = match heads[head] {
+          Some(head) if head > 0 && head <= tokens.len() => {
+            tokens[head - 1].chunk.map_or(-1, |chunk| chunk as i32)
+          }
+          _ => -1,
+        };
+      }
+      let head = chunk.token_pos + chunk.head_pos;
+      chunk.func_pos = derive_func(range, head, |index| {
+        tokens[index]
+          .morph_features(CABOCHA_POSSET_TYPE::IPA)
+          .is_functional()
+      }) - chunk.token_pos;
+    }
+
+    let sentence = text.unwrap_or_else(|| {
+      let mut sentence = String::new();
+      for (token, spaced) in tokens.iter().zip(space_after.iter()) {
+        sentence.push_str(&token.surface);
+        if *spaced {
+          sentence.push(' ');
+        }
+      }
+      sentence.trim_end().to_string()
+    });
+    Ok(OwnedTree {
+      sentence,
+      charset: None,
+      posset: Some(CABOCHA_POSSET_TYPE::IPA),
+      output_layer: None,
+      chunks,
+      tokens,
+    })
+  }
+}
+
+impl Tree {
+  pub fn to_conllu(&self) -> Result<String> {
+    self.to_owned_tree()?.to_conllu()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::*;
+  use crate::parser::Parser;
+
+  const CONLLU: &str = "# text = 一郎は贈った。
+1\t一郎\t一郎\tPROPN\t名詞-固有名詞-人名-名\t_\t3\tdep\t_\tBunsetuBILabel=B|NE=B-PERSON|SpaceAfter=No
+2\tは\tは\tADP\t助詞-係助詞\t_\t1\tcase\t_\tBunsetuBILabel=I|SpaceAfter=No
+3\t贈っ\t贈る\tVERB\t動詞-自立\tConjForm=連用タ接続|ConjType=五段・ラ行\t0\troot\t_\tBunsetuBILabel=B|SpaceAfter=No
+4\tた\tた\tAUX\t助動詞\tConjForm=基本形|ConjType=特殊・タ\t3\taux\t_\tBunsetuBILabel=I|SpaceAfter=No
+5\t。\t。\tPUNCT\t記号-句点\t_\t3\tpunct\t_\tBunsetuBILabel=I
+
+";
+
+  const LATTICE: &str = "* 0 1D 0/1 0.000000
+一郎\t名詞,固有名詞,人名,名,*,*,一郎,イチロウ,イチロー\tB-PERSON
+は\t助詞,係助詞,*,*,*,*,は,ハ,ワ\tO
+* 1 -1D 0/1 0.000000
+贈っ\t動詞,自立,*,*,五段・ラ行,連用タ接続,贈る,オクッ,オクッ\tO
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ\tO
+。\t記号,句点,*,*,*,*,。,。,。\tO
+EOS
+";
+
+  fn tree() -> OwnedTree {
+    from_lattice(LATTICE)
+  }
+
+  #[test]
+  fn test_to_conllu() {
+    assert_eq!(CONLLU, tree().to_conllu().unwrap());
+  }
+
+  #[test]
+  fn test_to_conllu_unaligned() {
+    let mut tree = tree();
+    tree.sentence = String::from("一郎は送った。");
+    let conllu = tree.to_conllu().unwrap();
+    assert!(!conllu.contains("SpaceAfter"));
+    assert_eq!(
+      CONLLU
+        .replace("|SpaceAfter=No", "")
+        .replace("一郎は贈った。", "一郎は送った。"),
+      conllu
+    );
+  }
+
+  #[test]
+  fn test_to_conllu_case_deprel() {
+    for (particle, deprel) in [("が", "nsubj"), ("を", "obj"), ("に", "obl")].iter() {
+      let tree = from_lattice(&LATTICE.replace(
+        "は\t助詞,係助詞,*,*,*,*,は,ハ,ワ",
+        &format!("{}\t助詞,格助詞,一般,*,*,*,{},*,*", particle, particle),
+      ));
+      let conllu = tree.to_conllu().unwrap();
+      let line = conllu.lines().nth(1).unwrap();
+      assert_eq!(Some(*deprel), line.split('\t').nth(7));
+    }
+  }
+
+  #[test]
+  fn test_from_conllu() {
+    let expected = tree();
+    let tree = OwnedTree::from_conllu(CONLLU).unwrap();
+    assert_eq!(expected.sentence, tree.sentence);
+    assert_eq!(expected.chunks, tree.chunks);
+    for (expected, token) in expected.tokens.iter().zip(tree.tokens.iter()) {
+      assert_eq!(expected.surface, token.surface);
+      assert_eq!(expected.chunk, token.chunk);
+      assert_eq!(&expected.feature_list[..7], &token.feature_list[..]);
+    }
+    assert_eq!(Some(CABOCHA_POSSET_TYPE::IPA), tree.posset);
+    assert_eq!("B-PERSON", tree.tokens[0].ne);
+    assert_eq!("", tree.tokens[1].ne);
+    assert_eq!(CONLLU, tree.to_conllu().unwrap());
+  }
+
+  #[test]
+  fn test_from_conllu_without_text() {
+    let input = "1-2\tab\t_\t_\t_\t_\t_\t_\t_\t_
+1\ta\ta\tX\t_\t_\t_\t_\t_\tSpaceAfter=No
+2\tb\tb\tX\t_\t_\t_\t_\t_\t_
+3\tc\tc\tX\t_\t_\t_\t_\t_\t_
+";
+    let tree = OwnedTree::from_conllu(input).unwrap();
+    assert_eq!("ab c", tree.sentence);
+    assert_eq!(3, tree.token_size());
+    assert!(tree.chunks.is_empty());
+    assert_eq!("*,*,*,*,*,*,a", tree.tokens[0].feature);
+  }
+
+  #[test]
+  fn test_from_conllu_malformed() {
+    assert!(matches!(
+      OwnedTree::from_conllu("1\ta\n"),
+      Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+      OwnedTree::from_conllu("1\ta\ta\tX\t_\t_\tx\t_\t_\t_\n"),
+      Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+      OwnedTree::from_conllu(&format!("{}{}", CONLLU, CONLLU)),
+      Err(Error::InvalidInput(_))
+    ));
+  }
+
+  #[test]
+  fn test_from_conllu_bad_heads_and_labels() {
+    let result = OwnedTree::from_conllu("1\ta\ta\tX\t_\t_\t9\tdep\t_\t_\n");
+    assert!(matches!(result, Err(Error::InvalidInput(message)) if message.starts_with("line 1:")));
+
+    let result = OwnedTree::from_conllu(
+      "# text = ab\n1\ta\ta\tX\t_\t_\t0\troot\t_\t_\n2\tb\tb\tX\t_\t_\t2\tdep\t_\t_\n",
+    );
+    assert!(matches!(result, Err(Error::InvalidInput(message)) if message.starts_with("line 3:")));
+
+    let result =
+      OwnedTree::from_conllu(&CONLLU.replacen("BunsetuBILabel=B", "BunsetuBILabel=I", 1));
+    assert!(matches!(result, Err(Error::InvalidInput(message)) if message.starts_with("line 2:")));
+  }
+
+  #[test]
+  fn test_tree_to_conllu() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    let conllu = tree.to_conllu().unwrap();
+    let owned = OwnedTree::from_conllu(&conllu).unwrap();
+    let expected = tree.to_owned_tree().unwrap();
+    assert_eq!(expected.token_size(), owned.token_size());
+    for (expected, chunk) in expected.chunks.iter().zip(owned.chunks.iter()) {
+      assert_eq!(expected.link, chunk.link);
+      assert_eq!(expected.head_pos, chunk.head_pos);
+      assert_eq!(expected.token_pos, chunk.token_pos);
+    }
+  }
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 pub const CABOCHA_EUC_JP: i32 = 0;
 pub const CABOCHA_CP932: i32 = 1;
 pub const CABOCHA_UTF8: i32 = 2;
@@ -26,6 +29,10 @@ pub const CABOCHA_OUTPUT_CHUNK: i32 = 2;
 pub const CABOCHA_OUTPUT_SELECTION: i32 = 3;
 pub const CABOCHA_OUTPUT_DEP: i32 = 4;
 
+pub const CABOCHA_NE_NONE: i32 = 0;
+pub const CABOCHA_NE_CONSTRAINT: i32 = 1;
+pub const CABOCHA_NE_NO_CONSTRAINT: i32 = 2;
+
 #[allow(dead_code)]
 pub const CABOCHA_TRAIN_NE: i32 = 0;
 #[allow(dead_code)]
@@ -34,7 +41,8 @@ pub const CABOCHA_TRAIN_CHUNK: i32 = 1;
 pub const CABOCHA_TRAIN_DEP: i32 = 2;
 
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CABOCHA_CHARSET_TYPE {
   EUC_JP = CABOCHA_EUC_JP as isize,
   CP932 = CABOCHA_CP932 as isize,
@@ -43,7 +51,8 @@ pub enum CABOCHA_CHARSET_TYPE {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CABOCHA_POSSET_TYPE {
   IPA = CABOCHA_IPA as isize,
   JUMAN = CABOCHA_JUMAN as isize,
@@ -51,7 +60,8 @@ pub enum CABOCHA_POSSET_TYPE {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CABOCHA_FORMAT {
   TREE = CABOCHA_FORMAT_TREE as isize,
   LATTICE = CABOCHA_FORMAT_LATTICE as isize,
@@ -62,7 +72,8 @@ pub enum CABOCHA_FORMAT {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CABOCHA_INPUT {
   RAW_SENTENCE = CABOCHA_INPUT_RAW_SENTENCE as isize,
   POS = CABOCHA_INPUT_POS as isize,
@@ -72,7 +83,8 @@ pub enum CABOCHA_INPUT {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CABOCHA_OUTPUT {
   RAW_SENTENCE = CABOCHA_OUTPUT_RAW_SENTENCE as isize,
   POS = CABOCHA_OUTPUT_POS as isize,
@@ -80,3 +92,12 @@ pub enum CABOCHA_OUTPUT {
   SELECTION = CABOCHA_OUTPUT_SELECTION as isize,
   DEP = CABOCHA_OUTPUT_DEP as isize,
 }
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CABOCHA_NE_MODE {
+  NONE = CABOCHA_NE_NONE as isize,
+  CONSTRAINT = CABOCHA_NE_CONSTRAINT as isize,
+  NO_CONSTRAINT = CABOCHA_NE_NO_CONSTRAINT as isize,
+}
@@ -0,0 +1,238 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::BufRead;
+use std::ops::Range;
+
+use super::error::*;
+use super::parser::*;
+use super::tree::*;
+
+fn is_terminal(c: char) -> bool {
+  matches!(c, '。' | '．' | '！' | '？' | '!' | '?')
+}
+
+const BRACKETS: [(char, char); 7] = [
+  ('「', '」'),
+  ('『', '』'),
+  ('（', '）'),
+  ('(', ')'),
+  ('【', '】'),
+  ('〈', '〉'),
+  ('《', '》'),
+];
+
+fn is_close(c: char) -> bool {
+  BRACKETS.iter().any(|&(_, close)| close == c)
+}
+
+/// Byte positions of the brackets in `text` that are closed on the same line,
+/// both the opening and the closing one.
+fn matched_brackets(text: &str) -> HashSet<usize> {
+  let mut matched = HashSet::new();
+  let mut open: Vec<(usize, char)> = Vec::new();
+  for (pos, c) in text.char_indices() {
+    if c == '\n' || c == '\r' {
+      open.clear();
+    } else if let Some(&(_, close)) = BRACKETS.iter().find(|&&(open, _)| open == c) {
+      open.push((pos, close));
+    } else if is_close(c) {
+      if let Some(index) = open.iter().rposition(|&(_, close)| close == c) {
+        matched.insert(open[index].0);
+        matched.insert(pos);
+        open.truncate(index);
+      }
+    }
+  }
+  matched
+}
+
+fn push_trimmed(sentences: &mut Vec<Range<usize>>, text: &str, range: Range<usize>) {
+  let sentence = &text[range.clone()];
+  let start = range.start + (sentence.len() - sentence.trim_start().len());
+  let end = range.end - (sentence.len() - sentence.trim_end().len());
+  if start < end {
+    sentences.push(start..end);
+  }
+}
+
+/// Split text into sentence byte ranges.
+///
+/// A sentence ends after `。．！？!?` outside brackets, together with any terminators
+/// and closing brackets that follow it, so `「はい。」と言った。` stays one sentence.
+/// Only brackets closed on the same line count, so an unclosed `(` such as in
+/// `顔(^^` does not hold back the split. Newlines always end a sentence.
+/// Surrounding whitespace is trimmed and blank sentences are skipped.
+pub fn split_sentences(text: &str) -> Vec<Range<usize>> {
+  let matched = matched_brackets(text);
+  let mut sentences = Vec::new();
+  let mut start = 0;
+  let mut depth = 0usize;
+  let mut chars = text.char_indices().peekable();
+  while let Some((pos, c)) = chars.next() {
+    if c == '\n' || c == '\r' {
+      push_trimmed(&mut sentences, text, start..pos);
+      start = pos + c.len_utf8();
+      depth = 0;
+    } else if matched.contains(&pos) {
+      if is_close(c) {
+        depth -= 1;
+      } else {
+        depth += 1;
+      }
+    } else if depth == 0 && is_terminal(c) {
+      let mut end = pos + c.len_utf8();
+      while let Some(&(pos, c)) = chars.peek() {
+        if !is_terminal(c) && !is_close(c) {
+          break;
+        }
+        end = pos + c.len_utf8();
+        chars.next();
+      }
+      push_trimmed(&mut sentences, text, start..end);
+      start = end;
+    }
+  }
+  push_trimmed(&mut sentences, text, start..text.len());
+  sentences
+}
+
+#[derive(Debug)]
+pub struct DocumentSentence {
+  /// Byte offset of the sentence in the document.
+  pub offset: usize,
+  pub text: String,
+  pub tree: Tree,
+}
+
+/// Reads a document line by line, splits it with `split_sentences`
+/// and parses one sentence per `next` call.
+pub struct DocumentParser<'p, R> {
+  parser: &'p mut Parser,
+  reader: R,
+  line: String,
+  line_offset: usize,
+  pending: VecDeque<Range<usize>>,
+  done: bool,
+}
+
+impl<'p, R: BufRead> DocumentParser<'p, R> {
+  pub fn new(parser: &'p mut Parser, reader: R) -> DocumentParser<'p, R> {
+    DocumentParser {
+      parser,
+      reader,
+      line: String::new(),
+      line_offset: 0,
+      pending: VecDeque::new(),
+      done: false,
+    }
+  }
+}
+
+impl<'p, R: BufRead> Iterator for DocumentParser<'p, R> {
+  type Item = Result<DocumentSentence>;
+
+  fn next(&mut self) -> Option<Result<DocumentSentence>> {
+    loop {
+      if let Some(range) = self.pending.pop_front() {
+        let offset = self.line_offset + range.start;
+        let text = self.line[range].to_string();
+        return Some(
+          self
+            .parser
+            .parse_to_tree(text.as_str())
+            .map(|tree| DocumentSentence { offset, text, tree }),
+        );
+      }
+      if self.done {
+        return None;
+      }
+      self.line_offset += self.line.len();
+      self.line.clear();
+      match self.reader.read_line(&mut self.line) {
+        Ok(0) => self.done = true,
+        Ok(_) => self.pending = split_sentences(&self.line).into(),
+        Err(err) => {
+          self.done = true;
+          return Some(Err(err.into()));
+        }
+      }
+    }
+  }
+}
+
+impl Parser {
+  pub fn parse_document<R: BufRead>(&mut self, reader: R) -> DocumentParser<'_, R> {
+    DocumentParser::new(self, reader)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn split(text: &str) -> Vec<&str> {
+    split_sentences(text)
+      .into_iter()
+      .map(|range| &text[range])
+      .collect()
+  }
+
+  #[test]
+  fn test_split_sentences() {
+    assert_eq!(
+      vec!["猫が鳴いた。", "本当？！", "犬も吠えた"],
+      split("猫が鳴いた。本当？！ 犬も吠えた")
+    );
+    assert_eq!(
+      vec!["「はい。そうです。」と彼は言った。", "（笑）"],
+      split("「はい。そうです。」と彼は言った。（笑）")
+    );
+    assert_eq!(vec!["終わった。」", "次"], split("終わった。」次"));
+    assert_eq!(
+      vec!["見出し", "「閉じない", "本文。"],
+      split("見出し\n\n「閉じない\r\n　本文。\n")
+    );
+    assert!(split(" \n　").is_empty());
+  }
+
+  #[test]
+  fn test_split_sentences_unbalanced_brackets() {
+    assert_eq!(
+      vec!["顔(^^ 次の文。", "また次。"],
+      split("顔(^^ 次の文。また次。")
+    );
+    assert_eq!(vec!["「(笑。次。」後。"], split("「(笑。次。」後。"));
+    assert_eq!(vec!["a)b。", "c。"], split("a)b。c。"));
+    assert_eq!(
+      vec!["「はい。（ね。」そう。"],
+      split("「はい。（ね。」そう。")
+    );
+  }
+
+  #[test]
+  fn test_document_parser() {
+    let mut parser = Parser::new("").unwrap();
+    let document = "一郎は二郎が描いた絵を三郎に贈った。「はい。」\n猫が鳴いた。";
+    let sentences = parser
+      .parse_document(document.as_bytes())
+      .collect::<Result<Vec<_>>>()
+      .unwrap();
+    assert_eq!(3, sentences.len());
+    for sentence in sentences.iter() {
+      assert_eq!(
+        sentence.text,
+        &document[sentence.offset..sentence.offset + sentence.text.len()]
+      );
+      assert_eq!(sentence.text, sentence.tree.sentence_str().unwrap());
+    }
+    assert_eq!("「はい。」", sentences[1].text);
+    assert_eq!(6, sentences[0].tree.chunk_size());
+  }
+
+  #[test]
+  fn test_document_parser_io_error() {
+    let mut parser = Parser::new("").unwrap();
+    let mut sentences = parser.parse_document(&b"\xff\xfe"[..]);
+    assert!(matches!(sentences.next(), Some(Err(Error::Io(_)))));
+    assert!(sentences.next().is_none());
+  }
+}
@@ -0,0 +1,158 @@
+use std::error;
+use std::ffi::NulError;
+use std::fmt;
+use std::io;
+use std::str::Utf8Error;
+
+use super::consts::*;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+  /// A model, dictionary or rc file given to cabocha could not be opened.
+  ModelNotFound(String),
+  /// cabocha rejected the option string.
+  BadOption(String),
+  /// The charset of the models does not match the requested one.
+  CharsetMismatch(String),
+  /// Any other failure reported by `cabocha_strerror`.
+  Other(String),
+  /// A string passed to cabocha contained an interior NUL byte.
+  InteriorNul(NulError),
+  /// A string returned by cabocha was not valid UTF-8.
+  InvalidEncoding(Utf8Error),
+  /// cabocha returned a null handle without an error message.
+  NullHandle,
+  /// cabocha failed to parse a sentence.
+  Parse(String),
+  /// cabocha failed to read the input of the given layer into a tree.
+  Read(CABOCHA_INPUT),
+  /// The surface of the token at this index could not be found in the sentence.
+  Alignment(usize),
+  /// Input given to a builder or reader is not well-formed.
+  InvalidInput(String),
+  /// The NE tag of the token at this index is an `I-` tag without a matching `B-` tag.
+  MalformedNe { index: usize, tag: String },
+  /// Reading the input failed.
+  Io(io::Error),
+}
+
+impl Error {
+  /// Classify a message returned by `cabocha_strerror`.
+  ///
+  /// CaboCha reports failed checks as `file(line) [condition] message`, with the
+  /// messages of its (MeCab-derived) option parser and file loaders, so the
+  /// known message texts are matched rather than single words.
+  pub(crate) fn from_message(message: String) -> Error {
+    if message.is_empty() {
+      return Error::NullHandle;
+    }
+    if message.contains("Incompatible charset") {
+      Error::CharsetMismatch(message)
+    } else if message.contains("unrecognized option `")
+      || message.contains("` requires an argument")
+      || message.contains("` doesn't allow an argument")
+    {
+      Error::BadOption(message)
+    } else if message.contains("no such file or directory: ") || message.contains("open failed: ") {
+      Error::ModelNotFound(message)
+    } else {
+      Error::Other(message)
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::ModelNotFound(message) => write!(f, "model not found: {}", message),
+      Error::BadOption(message) => write!(f, "bad option: {}", message),
+      Error::CharsetMismatch(message) => write!(f, "charset mismatch: {}", message),
+      Error::Other(message) => write!(f, "cabocha error: {}", message),
+      Error::InteriorNul(err) => write!(f, "interior nul byte: {}", err),
+      Error::InvalidEncoding(err) => write!(f, "invalid encoding: {}", err),
+      Error::NullHandle => write!(f, "cabocha returned a null handle"),
+      Error::Parse(message) => write!(f, "parse failure: {}", message),
+      Error::Read(layer) => write!(f, "read failure on input layer {:?}", layer),
+      Error::Alignment(index) => write!(f, "token {} is not found in the sentence", index),
+      Error::InvalidInput(message) => write!(f, "invalid input: {}", message),
+      Error::MalformedNe { index, tag } => {
+        write!(f, "malformed NE tag {:?} on token {}", tag, index)
+      }
+      Error::Io(err) => write!(f, "io error: {}", err),
+    }
+  }
+}
+
+impl error::Error for Error {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      Error::InteriorNul(err) => Some(err),
+      Error::InvalidEncoding(err) => Some(err),
+      Error::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<NulError> for Error {
+  fn from(err: NulError) -> Error {
+    Error::InteriorNul(err)
+  }
+}
+
+impl From<Utf8Error> for Error {
+  fn from(err: Utf8Error) -> Error {
+    Error::InvalidEncoding(err)
+  }
+}
+
+impl From<io::Error> for Error {
+  fn from(err: io::Error) -> Error {
+    Error::Io(err)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_from_message() {
+    for message in [
+      "param.cpp(69) [ifs] no such file or directory: /usr/local/etc/cabocharc",
+      "tagger.cpp(151) [load_dictionary_resource(param)] param.cpp(69) [ifs] no such file or directory: /usr/local/etc/mecabrc",
+      "mmap.h(153) [(fd = ::open(filename, flag | O_BINARY)) >= 0] open failed: /no/such/dep.ipa.model",
+    ]
+    .iter()
+    {
+      let err = Error::from_message(message.to_string());
+      assert!(matches!(err, Error::ModelNotFound(_)), "{}", message);
+    }
+
+    for message in [
+      "unrecognized option `--foo`",
+      "`--parser-model` requires an argument",
+      "`--help` doesn't allow an argument",
+    ]
+    .iter()
+    {
+      let err = Error::from_message(message.to_string());
+      assert!(matches!(err, Error::BadOption(_)), "{}", message);
+    }
+
+    let err = Error::from_message(String::from(
+      "morph.cpp(86) [charset == mecab_charset] Incompatible charset: MeCab charset is EUC-JP, Your charset is UTF-8",
+    ));
+    assert!(matches!(err, Error::CharsetMismatch(_)));
+
+    let err = Error::from_message(String::from(
+      "svm.cpp(135) [version == MODEL_VERSION] model version is different",
+    ));
+    assert!(matches!(err, Error::Other(_)));
+
+    let err = Error::from_message(String::new());
+    assert!(matches!(err, Error::NullHandle));
+  }
+}
@@ -0,0 +1,46 @@
+use super::consts::*;
+use super::owned::*;
+
+/// Build the snapshot of a lattice without the C library.
+pub(crate) fn from_lattice(lattice: &str) -> OwnedTree {
+  let mut chunks = Vec::new();
+  let mut tokens = Vec::new();
+  for line in lattice.lines().filter(|line| *line != "EOS") {
+    if let Some(chunk) = line.strip_prefix("* ") {
+      let fields = chunk.split(' ').collect::<Vec<_>>();
+      let (head_pos, func_pos) = fields[2].split_once('/').unwrap();
+      chunks.push(OwnedChunk {
+        link: fields[1].trim_end_matches('D').parse().unwrap(),
+        head_pos: head_pos.parse().unwrap(),
+        func_pos: func_pos.parse().unwrap(),
+        token_size: 0,
+        token_pos: tokens.len(),
+        score: fields[3].parse().unwrap(),
+        feature_list: Vec::new(),
+        additional_info: String::new(),
+      });
+    } else {
+      let fields = line.split('\t').collect::<Vec<_>>();
+      if let Some(chunk) = chunks.last_mut() {
+        chunk.token_size += 1;
+      }
+      tokens.push(OwnedToken {
+        surface: fields[0].to_string(),
+        normalized_surface: fields[0].to_string(),
+        feature: fields[1].to_string(),
+        feature_list: fields[1].split(',').map(String::from).collect(),
+        ne: fields.get(2).map(|ne| ne.to_string()).unwrap_or_default(),
+        additional_info: String::new(),
+        chunk: chunks.len().checked_sub(1),
+      });
+    }
+  }
+  OwnedTree {
+    sentence: tokens.iter().map(|token| token.surface.as_str()).collect(),
+    charset: Some(CABOCHA_CHARSET_TYPE::UTF8),
+    posset: Some(CABOCHA_POSSET_TYPE::IPA),
+    output_layer: Some(CABOCHA_OUTPUT::DEP),
+    chunks,
+    tokens,
+  }
+}
@@ -0,0 +1,240 @@
+use std::io::BufRead;
+
+use super::error::*;
+use super::tree::*;
+
+/// Iterates over the `EOS`-terminated sentences of cabocha lattice output
+/// and rebuilds each one with `Tree::from_lattice_str`.
+pub struct LatticeReader<R> {
+  reader: R,
+  line: String,
+  done: bool,
+}
+
+impl<R: BufRead> LatticeReader<R> {
+  pub fn new(reader: R) -> LatticeReader<R> {
+    LatticeReader {
+      reader,
+      line: String::new(),
+      done: false,
+    }
+  }
+
+  /// Read the next block of lines up to and including `EOS`,
+  /// or `None` when only blank lines are left.
+  fn next_block(&mut self) -> Result<Option<String>> {
+    let mut block = String::new();
+    loop {
+      self.line.clear();
+      if self.reader.read_line(&mut self.line)? == 0 {
+        self.done = true;
+        return Ok(if block.is_empty() { None } else { Some(block) });
+      }
+      let line = self.line.trim_end_matches(['\n', '\r']);
+      if line.is_empty() {
+        continue;
+      }
+      block.push_str(line);
+      block.push('\n');
+      if line == "EOS" {
+        return Ok(Some(block));
+      }
+    }
+  }
+}
+
+impl<R: BufRead> Iterator for LatticeReader<R> {
+  type Item = Result<Tree>;
+
+  fn next(&mut self) -> Option<Result<Tree>> {
+    if self.done {
+      return None;
+    }
+    match self.next_block() {
+      Ok(block) => block.map(|block| Tree::from_lattice_str(&block)),
+      Err(err) => {
+        self.done = true;
+        Some(Err(err))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::consts::*;
+  use crate::writer::*;
+
+  const LATTICE: &str = "* 0 2D 0/1 -1.663431
+一郎\t名詞,固有名詞,人名,名,*,*,一郎,イチロウ,イチロー
+は\t助詞,係助詞,*,*,*,*,は,ハ,ワ
+* 1 2D 0/1 0.916559
+絵\t名詞,一般,*,*,*,*,絵,エ,エ
+を\t助詞,格助詞,一般,*,*,*,を,ヲ,ヲ
+* 2 -1D 0/1 0.000000
+贈っ\t動詞,自立,*,*,五段・ラ行,連用タ接続,贈る,オクッ,オクッ
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ
+。\t記号,句点,*,*,*,*,。,。,。
+EOS
+";
+
+  const CONLL: &str = "1\t一郎\t一郎\t名詞\t名詞-固有名詞-人名-名\t名詞|固有名詞|人名|名|*|*|一郎|イチロウ|イチロー\t5\tD\t_\t_
+2\tは\tは\t助詞\t助詞-係助詞\t助詞|係助詞|*|*|*|*|は|ハ|ワ\t1\t_\t_\t_
+3\t絵\t絵\t名詞\t名詞-一般\t名詞|一般|*|*|*|*|絵|エ|エ\t5\tD\t_\t_
+4\tを\tを\t助詞\t助詞-格助詞-一般\t助詞|格助詞|一般|*|*|*|を|ヲ|ヲ\t3\t_\t_\t_
+5\t贈っ\t贈る\t動詞\t動詞-自立\t動詞|自立|*|*|五段・ラ行|連用タ接続|贈る|オクッ|オクッ\t0\tD\t_\t_
+6\tた\tた\t助動詞\t助動詞\t助動詞|*|*|*|特殊・タ|基本形|た|タ|タ\t5\t_\t_\t_
+7\t。\t。\t記号\t記号-句点\t記号|句点|*|*|*|*|。|。|。\t5\t_\t_\t_
+
+";
+
+  #[test]
+  fn test_from_lattice_str() {
+    let tree = Tree::from_lattice_str(LATTICE).unwrap();
+    assert_eq!(3, tree.chunk_size());
+    assert_eq!(7, tree.token_size());
+    let chunk = tree.chunk(0).unwrap();
+    assert_eq!(2, chunk.link());
+    assert_eq!(1, chunk.func_pos());
+    assert!((chunk.score() + 1.663_431).abs() < 1.0e-6);
+    assert_eq!("贈っ", tree.token(4).unwrap().surface_str().unwrap());
+    assert_eq!(
+      LATTICE,
+      tree
+        .to_string(crate::consts::CABOCHA_FORMAT::LATTICE)
+        .unwrap()
+    );
+  }
+
+  #[test]
+  fn test_from_lattice_str_without_chunks() {
+    let tree = Tree::from_lattice_str("猫\t名詞,一般,*,*,*,*,猫,ネコ,ネコ\nEOS\n").unwrap();
+    assert_eq!(0, tree.chunk_size());
+    assert_eq!(1, tree.token_size());
+  }
+
+  #[test]
+  fn test_from_lattice_str_empty() {
+    assert!(matches!(
+      Tree::from_lattice_str("EOS\n"),
+      Err(Error::InvalidInput(_))
+    ));
+  }
+
+  #[test]
+  fn test_from_lattice_str_several_sentences() {
+    let input = format!("{}\n{}", LATTICE, LATTICE);
+    assert!(matches!(
+      Tree::from_lattice_str(&input),
+      Err(Error::InvalidInput(_))
+    ));
+  }
+
+  #[test]
+  fn test_from_conll_str() {
+    let tree = Tree::from_conll_str(CONLL).unwrap();
+    assert_eq!(3, tree.chunk_size());
+    assert_eq!(7, tree.token_size());
+    let links = tree
+      .chunks()
+      .iter()
+      .map(|chunk| chunk.link())
+      .collect::<Vec<_>>();
+    assert_eq!(vec![2, 2, -1], links);
+    let chunk = tree.chunk(2).unwrap();
+    assert_eq!(
+      (4, 0, 1),
+      (chunk.token_pos(), chunk.head_pos(), chunk.func_pos())
+    );
+    assert_eq!(
+      "動詞,自立,*,*,五段・ラ行,連用タ接続,贈る,オクッ,オクッ",
+      tree.token(4).unwrap().feature_str().unwrap()
+    );
+    assert_eq!(
+      CONLL,
+      tree
+        .to_owned_tree()
+        .unwrap()
+        .to_string_with(CABOCHA_FORMAT::CONLL, &WriteOptions::default())
+        .unwrap()
+    );
+  }
+
+  #[test]
+  fn test_tree_builder_from_conll() {
+    let builder = TreeBuilder::from_conll(CONLL).unwrap();
+    assert_eq!(CABOCHA_INPUT::DEP, builder.input_layer());
+    assert_eq!(
+      LATTICE
+        .replace("-1.663431", "0.000000")
+        .replace("0.916559", "0.000000"),
+      builder.to_lattice().unwrap()
+    );
+
+    let conll = CONLL
+      .lines()
+      .map(|line| {
+        let mut fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() == 10 {
+          fields[6] = "_";
+          fields[7] = "_";
+        }
+        fields.join("\t") + "\n"
+      })
+      .collect::<String>();
+    let builder = TreeBuilder::from_conll(&conll).unwrap();
+    assert_eq!(CABOCHA_INPUT::POS, builder.input_layer());
+  }
+
+  #[test]
+  fn test_from_conll_str_invalid() {
+    let token = |id: usize, head: &str, deprel: &str| {
+      format!(
+        "{}\t猫\t猫\t名詞\t名詞-一般\t名詞|一般|*|*|*|*|猫|ネコ|ネコ\t{}\t{}\t_\t_\n",
+        id, head, deprel
+      )
+    };
+    for input in [
+      String::new(),
+      token(1, "0", "_"),
+      token(1, "1", "D"),
+      token(1, "2", "D"),
+      token(2, "0", "D"),
+      token(1, "0", "nsubj"),
+      format!("{}{}", token(1, "0", "D"), token(2, "_", "_")),
+      format!("{}{}", token(1, "2", "_"), token(2, "0", "D")) + &token(3, "1", "_"),
+      format!("{}\n{}", token(1, "0", "D"), token(1, "0", "D")),
+      String::from("1\t猫\n"),
+    ]
+    .iter()
+    {
+      assert!(
+        matches!(TreeBuilder::from_conll(input), Err(Error::InvalidInput(_))),
+        "{:?}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn test_lattice_reader() {
+    let input = format!(
+      "{}\r\n{}猫\t名詞,一般,*,*,*,*,猫,ネコ,ネコ\n",
+      LATTICE, LATTICE
+    );
+    let trees = LatticeReader::new(input.as_bytes())
+      .collect::<Result<Vec<_>>>()
+      .unwrap();
+    assert_eq!(3, trees.len());
+    assert_eq!(3, trees[1].chunk_size());
+    assert_eq!(1, trees[2].token_size());
+  }
+
+  #[test]
+  fn test_lattice_reader_io_error() {
+    let mut reader = LatticeReader::new(&b"\xff\n"[..]);
+    assert!(matches!(reader.next(), Some(Err(Error::Io(_)))));
+    assert!(reader.next().is_none());
+  }
+}
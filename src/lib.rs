@@ -3,10 +3,30 @@
 #![crate_type = "dylib"]
 #![crate_type = "rlib"]
 
-mod chunk;
+pub mod chunk;
+pub mod chunk_features;
+pub mod conllu;
 pub mod consts;
+pub mod document;
+mod error;
+#[cfg(test)]
+mod fixtures;
+pub mod lattice;
+pub mod morph;
+pub mod ne;
+pub mod owned;
+#[cfg(feature = "rayon")]
+mod par;
 pub mod parser;
+pub mod pool;
+pub mod render;
 mod sys;
-mod token;
-mod tree;
+pub mod token;
+pub mod tree;
 mod utils;
+pub mod writer;
+pub mod xml;
+
+pub use error::{Error, Result};
+#[cfg(feature = "rayon")]
+pub use par::par_parse;
@@ -0,0 +1,308 @@
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::consts::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MorphFeatures {
+  pub posset: CABOCHA_POSSET_TYPE,
+  pub pos: String,
+  pub pos_detail1: Option<String>,
+  pub pos_detail2: Option<String>,
+  pub pos_detail3: Option<String>,
+  pub conjugation_type: Option<String>,
+  pub conjugation_form: Option<String>,
+  pub base_form: Option<String>,
+  pub reading: Option<String>,
+  pub pronunciation: Option<String>,
+}
+
+struct Columns {
+  pos_detail1: Option<usize>,
+  pos_detail2: Option<usize>,
+  pos_detail3: Option<usize>,
+  conjugation_type: Option<usize>,
+  conjugation_form: Option<usize>,
+  base_form: Option<usize>,
+  reading: Option<usize>,
+  pronunciation: Option<usize>,
+}
+
+// 品詞,品詞細分類1,品詞細分類2,品詞細分類3,活用型,活用形,原形,読み,発音
+const IPA_COLUMNS: Columns = Columns {
+  pos_detail1: Some(1),
+  pos_detail2: Some(2),
+  pos_detail3: Some(3),
+  conjugation_type: Some(4),
+  conjugation_form: Some(5),
+  base_form: Some(6),
+  reading: Some(7),
+  pronunciation: Some(8),
+};
+
+// 品詞,品詞細分類,活用型,活用形,原形,読み,意味情報
+const JUMAN_COLUMNS: Columns = Columns {
+  pos_detail1: Some(1),
+  pos_detail2: None,
+  pos_detail3: None,
+  conjugation_type: Some(2),
+  conjugation_form: Some(3),
+  base_form: Some(4),
+  reading: Some(5),
+  pronunciation: None,
+};
+
+// pos1,pos2,pos3,pos4,cType,cForm,lForm,lemma,orth,pron,...
+const UNIDIC_COLUMNS: Columns = Columns {
+  pos_detail1: Some(1),
+  pos_detail2: Some(2),
+  pos_detail3: Some(3),
+  conjugation_type: Some(4),
+  conjugation_form: Some(5),
+  base_form: Some(7),
+  reading: Some(6),
+  pronunciation: Some(9),
+};
+
+fn particle(detail1: &str) -> &'static str {
+  match detail1 {
+    "接続助詞" => "SCONJ",
+    "終助詞" => "PART",
+    _ => "ADP",
+  }
+}
+
+impl MorphFeatures {
+  /// Decode a token feature list according to `posset`.
+  /// `*` and missing columns become `None`.
+  /// For UniDic, `base_form` is the lemma and `reading` its lemma reading (lForm).
+  pub fn decode<S: AsRef<str>>(posset: CABOCHA_POSSET_TYPE, features: &[S]) -> MorphFeatures {
+    let columns = match posset {
+      CABOCHA_POSSET_TYPE::IPA => &IPA_COLUMNS,
+      CABOCHA_POSSET_TYPE::JUMAN => &JUMAN_COLUMNS,
+      CABOCHA_POSSET_TYPE::UNIDIC => &UNIDIC_COLUMNS,
+    };
+    let column = |index: Option<usize>| {
+      index
+        .and_then(|index| features.get(index))
+        .map(|feature| feature.as_ref())
+        .filter(|feature| !feature.is_empty() && *feature != "*")
+        .map(str::to_string)
+    };
+    MorphFeatures {
+      posset,
+      pos: column(Some(0)).unwrap_or_default(),
+      pos_detail1: column(columns.pos_detail1),
+      pos_detail2: column(columns.pos_detail2),
+      pos_detail3: column(columns.pos_detail3),
+      conjugation_type: column(columns.conjugation_type),
+      conjugation_form: column(columns.conjugation_form),
+      base_form: column(columns.base_form),
+      reading: column(columns.reading),
+      pronunciation: column(columns.pronunciation),
+    }
+  }
+
+  /// Universal Dependencies part-of-speech tag, following the UD Japanese treebanks.
+  pub fn upos(&self) -> &'static str {
+    let detail1 = self.pos_detail1.as_deref().unwrap_or_default();
+    match self.posset {
+      CABOCHA_POSSET_TYPE::IPA => match self.pos.as_str() {
+        "名詞" => match detail1 {
+          "固有名詞" => "PROPN",
+          "代名詞" => "PRON",
+          "数" => "NUM",
+          _ => "NOUN",
+        },
+        "動詞" if detail1 == "非自立" => "AUX",
+        "動詞" => "VERB",
+        "形容詞" => "ADJ",
+        "副詞" => "ADV",
+        "連体詞" => "DET",
+        "接続詞" => "CCONJ",
+        "助詞" => particle(detail1),
+        "助動詞" => "AUX",
+        "感動詞" | "フィラー" => "INTJ",
+        "記号" => match detail1 {
+          "句点" | "読点" | "括弧開" | "括弧閉" => "PUNCT",
+          _ => "SYM",
+        },
+        "接頭詞" => "NOUN",
+        _ => "X",
+      },
+      CABOCHA_POSSET_TYPE::JUMAN => match self.pos.as_str() {
+        "名詞" => match detail1 {
+          "固有名詞" | "人名" | "地名" | "組織名" => "PROPN",
+          "数詞" => "NUM",
+          _ => "NOUN",
+        },
+        "動詞" => "VERB",
+        "形容詞" => "ADJ",
+        "判定詞" | "助動詞" => "AUX",
+        "副詞" => "ADV",
+        "連体詞" => "DET",
+        "接続詞" => "CCONJ",
+        "助詞" => particle(detail1),
+        "指示詞" => "PRON",
+        "感動詞" => "INTJ",
+        "特殊" => match detail1 {
+          "句点" | "読点" | "括弧始" | "括弧終" => "PUNCT",
+          _ => "SYM",
+        },
+        "接頭辞" | "接尾辞" => "NOUN",
+        _ => "X",
+      },
+      CABOCHA_POSSET_TYPE::UNIDIC => match self.pos.as_str() {
+        "名詞" => match detail1 {
+          "固有名詞" => "PROPN",
+          "数詞" => "NUM",
+          _ => "NOUN",
+        },
+        "代名詞" => "PRON",
+        "動詞" => "VERB",
+        "形容詞" | "形状詞" => "ADJ",
+        "助動詞" => "AUX",
+        "副詞" => "ADV",
+        "連体詞" => "DET",
+        "接続詞" => "CCONJ",
+        "助詞" => particle(detail1),
+        "感動詞" => "INTJ",
+        "補助記号" => "PUNCT",
+        "記号" => "SYM",
+        "接頭辞" | "接尾辞" => "NOUN",
+        _ => "X",
+      },
+    }
+  }
+
+  /// Whether the token is a function word, `None` for punctuation and symbols.
+  pub(crate) fn is_functional(&self) -> Option<bool> {
+    match self.upos() {
+      "ADP" | "AUX" | "PART" | "SCONJ" => Some(true),
+      "PUNCT" | "SYM" => None,
+      _ => Some(false),
+    }
+  }
+
+  pub fn pos_details(&self) -> Vec<&str> {
+    [&self.pos_detail1, &self.pos_detail2, &self.pos_detail3]
+      .iter()
+      .filter_map(|detail| detail.as_deref())
+      .collect()
+  }
+}
+
+/// The last function word from `head` to the end of a chunk's `tokens`, or `head`.
+pub(crate) fn derive_func<F>(tokens: Range<usize>, head: usize, is_functional: F) -> usize
+where
+  F: Fn(usize) -> Option<bool>,
+{
+  (head..tokens.end)
+    .rev()
+    .find(|&index| is_functional(index) == Some(true))
+    .unwrap_or(head)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Parser;
+
+  #[test]
+  fn test_decode_ipa() {
+    let features = "動詞,自立,*,*,五段・ラ行,連用タ接続,贈る,オクッ,オクッ"
+      .split(',')
+      .collect::<Vec<_>>();
+    let morph = MorphFeatures::decode(CABOCHA_POSSET_TYPE::IPA, &features);
+    assert_eq!("動詞", morph.pos);
+    assert_eq!(Some("自立"), morph.pos_detail1.as_deref());
+    assert_eq!(None, morph.pos_detail2);
+    assert_eq!(Some("五段・ラ行"), morph.conjugation_type.as_deref());
+    assert_eq!(Some("連用タ接続"), morph.conjugation_form.as_deref());
+    assert_eq!(Some("贈る"), morph.base_form.as_deref());
+    assert_eq!(Some("オクッ"), morph.reading.as_deref());
+    assert_eq!(Some("オクッ"), morph.pronunciation.as_deref());
+    assert_eq!(vec!["自立"], morph.pos_details());
+  }
+
+  #[test]
+  fn test_decode_ipa_unknown_word() {
+    let features = vec!["名詞", "一般", "*", "*", "*", "*", "*"];
+    let morph = MorphFeatures::decode(CABOCHA_POSSET_TYPE::IPA, &features);
+    assert_eq!("名詞", morph.pos);
+    assert_eq!(None, morph.base_form);
+    assert_eq!(None, morph.reading);
+    assert_eq!(None, morph.pronunciation);
+  }
+
+  #[test]
+  fn test_upos() {
+    let upos = |posset, feature: &str| {
+      MorphFeatures::decode(posset, &feature.split(',').collect::<Vec<_>>()).upos()
+    };
+    assert_eq!(
+      "PROPN",
+      upos(CABOCHA_POSSET_TYPE::IPA, "名詞,固有名詞,人名,名")
+    );
+    assert_eq!("ADP", upos(CABOCHA_POSSET_TYPE::IPA, "助詞,係助詞"));
+    assert_eq!("SCONJ", upos(CABOCHA_POSSET_TYPE::IPA, "助詞,接続助詞"));
+    assert_eq!("AUX", upos(CABOCHA_POSSET_TYPE::IPA, "動詞,非自立"));
+    assert_eq!("PUNCT", upos(CABOCHA_POSSET_TYPE::IPA, "記号,句点"));
+    assert_eq!("AUX", upos(CABOCHA_POSSET_TYPE::JUMAN, "判定詞,*"));
+    assert_eq!("PROPN", upos(CABOCHA_POSSET_TYPE::JUMAN, "名詞,人名"));
+    assert_eq!("ADJ", upos(CABOCHA_POSSET_TYPE::UNIDIC, "形状詞,一般"));
+    assert_eq!("PUNCT", upos(CABOCHA_POSSET_TYPE::UNIDIC, "補助記号,句点"));
+    assert_eq!("X", upos(CABOCHA_POSSET_TYPE::UNIDIC, "空白"));
+  }
+
+  #[test]
+  fn test_decode_juman() {
+    let features = "動詞,*,子音動詞ラ行,タ形,贈る,おくった,代表表記:贈る/おくる"
+      .split(',')
+      .collect::<Vec<_>>();
+    let morph = MorphFeatures::decode(CABOCHA_POSSET_TYPE::JUMAN, &features);
+    assert_eq!("動詞", morph.pos);
+    assert_eq!(None, morph.pos_detail1);
+    assert_eq!(Some("子音動詞ラ行"), morph.conjugation_type.as_deref());
+    assert_eq!(Some("タ形"), morph.conjugation_form.as_deref());
+    assert_eq!(Some("贈る"), morph.base_form.as_deref());
+    assert_eq!(Some("おくった"), morph.reading.as_deref());
+    assert_eq!(None, morph.pronunciation);
+  }
+
+  #[test]
+  fn test_decode_unidic() {
+    let features = "動詞,一般,*,*,五段-ラ行,連用形-促音便,オクル,贈る,贈っ,オクッ,贈る,オクル,和"
+      .split(',')
+      .collect::<Vec<_>>();
+    let morph = MorphFeatures::decode(CABOCHA_POSSET_TYPE::UNIDIC, &features);
+    assert_eq!("動詞", morph.pos);
+    assert_eq!(Some("一般"), morph.pos_detail1.as_deref());
+    assert_eq!(Some("五段-ラ行"), morph.conjugation_type.as_deref());
+    assert_eq!(Some("連用形-促音便"), morph.conjugation_form.as_deref());
+    assert_eq!(Some("贈る"), morph.base_form.as_deref());
+    assert_eq!(Some("オクル"), morph.reading.as_deref());
+    assert_eq!(Some("オクッ"), morph.pronunciation.as_deref());
+  }
+
+  #[test]
+  fn test_token_morph_features() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    let morph = tree.token(4).unwrap().morph_features().unwrap();
+    assert_eq!(CABOCHA_POSSET_TYPE::IPA, morph.posset);
+    assert_eq!("動詞", morph.pos);
+    assert_eq!(Some("描く"), morph.base_form.as_deref());
+
+    let owned = tree.to_owned_tree().unwrap();
+    assert_eq!(
+      morph,
+      owned.tokens[4].morph_features(CABOCHA_POSSET_TYPE::IPA)
+    );
+  }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::error::*;
+use super::utils::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EntityType {
+  Person,
+  Location,
+  Organization,
+  Artifact,
+  Date,
+  Time,
+  Money,
+  Percent,
+  Other(String),
+}
+
+impl EntityType {
+  pub fn as_str(&self) -> &str {
+    match self {
+      EntityType::Person => "PERSON",
+      EntityType::Location => "LOCATION",
+      EntityType::Organization => "ORGANIZATION",
+      EntityType::Artifact => "ARTIFACT",
+      EntityType::Date => "DATE",
+      EntityType::Time => "TIME",
+      EntityType::Money => "MONEY",
+      EntityType::Percent => "PERCENT",
+      EntityType::Other(name) => name,
+    }
+  }
+}
+
+impl<'a> From<&'a str> for EntityType {
+  fn from(name: &'a str) -> EntityType {
+    match name {
+      "PERSON" => EntityType::Person,
+      "LOCATION" => EntityType::Location,
+      "ORGANIZATION" => EntityType::Organization,
+      "ARTIFACT" => EntityType::Artifact,
+      "DATE" => EntityType::Date,
+      "TIME" => EntityType::Time,
+      "MONEY" => EntityType::Money,
+      "PERCENT" => EntityType::Percent,
+      _ => EntityType::Other(name.to_string()),
+    }
+  }
+}
+
+impl fmt::Display for EntityType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NamedEntity {
+  pub entity_type: EntityType,
+  /// Token indices covered by the entity.
+  pub tokens: Range<usize>,
+  /// Byte offsets into the sentence.
+  pub byte_span: Range<usize>,
+  /// Character offsets into the sentence.
+  pub char_span: Range<usize>,
+  pub surface: String,
+}
+
+/// Group per-token IOB tags (`B-PERSON`, `I-PERSON`, `O`) into typed token ranges.
+/// An `I-` tag that does not continue an entity of the same type starts a new one
+/// and any other tag counts as `O`; use `validate_iob` to reject such tags.
+pub fn decode_iob<S: AsRef<str>>(tags: &[S]) -> Vec<(EntityType, Range<usize>)> {
+  let mut entities: Vec<(EntityType, Range<usize>)> = Vec::new();
+  let mut open = false;
+  for (index, tag) in tags.iter().enumerate() {
+    let tag = tag.as_ref();
+    if let Some(name) = tag.strip_prefix("B-") {
+      entities.push((EntityType::from(name), index..index + 1));
+      open = true;
+    } else if let Some(name) = tag.strip_prefix("I-") {
+      match entities.last_mut() {
+        Some((entity_type, tokens)) if open && entity_type.as_str() == name => {
+          tokens.end = index + 1;
+        }
+        _ => entities.push((EntityType::from(name), index..index + 1)),
+      }
+      open = true;
+    } else {
+      open = false;
+    }
+  }
+  entities
+}
+
+/// Check that every tag is empty, `O`, `B-*`, or an `I-*` continuing an
+/// entity of the same type, reporting the first that is not.
+pub fn validate_iob<S: AsRef<str>>(tags: &[S]) -> Result<()> {
+  let mut open: Option<&str> = None;
+  for (index, tag) in tags.iter().enumerate() {
+    let tag = tag.as_ref();
+    if tag.is_empty() || tag == "O" {
+      open = None;
+    } else if let Some(name) = tag.strip_prefix("B-") {
+      open = Some(name);
+    } else {
+      match tag.strip_prefix("I-") {
+        Some(name) if Some(name) == open => {}
+        _ => {
+          return Err(Error::MalformedNe {
+            index,
+            tag: tag.to_string(),
+          })
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+pub fn named_entities<S: AsRef<str>, T: AsRef<str>>(
+  sentence: &str,
+  surfaces: &[S],
+  tags: &[T],
+) -> Result<Vec<NamedEntity>> {
+  let entities = decode_iob(tags);
+  if entities.is_empty() {
+    return Ok(Vec::new());
+  }
+  let spans = align_surfaces(sentence, surfaces)?;
+  let char_offsets = byte_to_char_offsets(sentence);
+  Ok(
+    entities
+      .into_iter()
+      .map(|(entity_type, tokens)| {
+        let byte_span = spans[tokens.start].start..spans[tokens.end - 1].end;
+        NamedEntity {
+          entity_type,
+          surface: sentence[byte_span.clone()].to_string(),
+          char_span: char_offsets[byte_span.start]..char_offsets[byte_span.end],
+          byte_span,
+          tokens,
+        }
+      })
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::consts::*;
+  use crate::parser::Parser;
+
+  #[test]
+  fn test_decode_iob() {
+    let tags = [
+      "B-PERSON",
+      "I-PERSON",
+      "O",
+      "B-LOCATION",
+      "B-DATE",
+      "",
+      "B-EVENT",
+    ];
+    let entities = decode_iob(&tags);
+    assert_eq!(
+      vec![
+        (EntityType::Person, 0..2),
+        (EntityType::Location, 3..4),
+        (EntityType::Date, 4..5),
+        (EntityType::Other(String::from("EVENT")), 6..7),
+      ],
+      entities
+    );
+  }
+
+  #[test]
+  fn test_decode_iob_stray_inside() {
+    assert_eq!(
+      vec![
+        (EntityType::Person, 1..3),
+        (EntityType::Location, 3..4),
+        (EntityType::Date, 5..6),
+      ],
+      decode_iob(&["O", "I-PERSON", "I-PERSON", "I-LOCATION", "X", "B-DATE"])
+    );
+  }
+
+  #[test]
+  fn test_validate_iob() {
+    assert!(validate_iob(&["B-PERSON", "I-PERSON", "O", "", "B-DATE"]).is_ok());
+
+    let result = validate_iob(&["O", "I-PERSON"]);
+    assert!(matches!(result, Err(Error::MalformedNe { index: 1, .. })));
+
+    let result = validate_iob(&["B-PERSON", "I-LOCATION"]);
+    assert!(matches!(result, Err(Error::MalformedNe { index: 1, .. })));
+
+    let result = validate_iob(&["B-PERSON", "O", "I-PERSON"]);
+    assert!(matches!(result, Err(Error::MalformedNe { index: 2, .. })));
+
+    let result = validate_iob(&["B-PERSON", "X"]);
+    assert!(matches!(result, Err(Error::MalformedNe { index: 1, .. })));
+  }
+
+  #[test]
+  fn test_named_entities_keeps_valid_spans() {
+    let entities = named_entities(
+      "一郎は東京へ",
+      &["一郎", "は", "東京", "へ"],
+      &["B-PERSON", "I-DATE", "B-LOCATION", "O"],
+    )
+    .unwrap();
+    assert_eq!(3, entities.len());
+    assert_eq!("一郎", entities[0].surface);
+    assert_eq!("東京", entities[2].surface);
+  }
+
+  #[test]
+  fn test_named_entities() {
+    let entities = named_entities(
+      "昨日 一郎は東京へ",
+      &["昨日", "一郎", "は", "東京", "へ"],
+      &["B-DATE", "B-PERSON", "O", "B-LOCATION", "O"],
+    )
+    .unwrap();
+    assert_eq!(3, entities.len());
+    assert_eq!(
+      NamedEntity {
+        entity_type: EntityType::Person,
+        tokens: 1..2,
+        byte_span: 7..13,
+        char_span: 3..5,
+        surface: String::from("一郎"),
+      },
+      entities[1]
+    );
+    assert_eq!(EntityType::Location, entities[2].entity_type);
+    assert_eq!(6..8, entities[2].char_span);
+  }
+
+  #[test]
+  fn test_tree_named_entities() {
+    let mut parser = Parser::builder()
+      .ne(CABOCHA_NE_MODE::CONSTRAINT)
+      .build()
+      .unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    assert!(tree.validate_ne().is_ok());
+    let entities = tree.named_entities().unwrap();
+    assert!(!entities.is_empty());
+    for entity in entities.iter() {
+      assert_eq!(
+        entity.surface,
+        &tree.sentence_str().unwrap()[entity.byte_span.clone()]
+      );
+    }
+    assert_eq!(
+      entities,
+      tree.to_owned_tree().unwrap().named_entities().unwrap()
+    );
+  }
+}
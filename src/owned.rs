@@ -0,0 +1,281 @@
+use std::convert::TryFrom;
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::chunk::*;
+use super::chunk_features::*;
+use super::consts::*;
+use super::error::*;
+use super::morph::*;
+use super::ne::*;
+use super::token::*;
+use super::tree::*;
+use super::utils::*;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedToken {
+  pub surface: String,
+  pub normalized_surface: String,
+  pub feature: String,
+  pub feature_list: Vec<String>,
+  pub ne: String,
+  pub additional_info: String,
+  /// Index of the chunk containing this token, if the tree has chunks.
+  pub chunk: Option<usize>,
+}
+
+impl OwnedToken {
+  pub fn from_token(token: &Token) -> Result<OwnedToken> {
+    Ok(OwnedToken {
+      surface: token.surface()?,
+      normalized_surface: token.normalized_surface()?,
+      feature: token.feature()?,
+      feature_list: token.feature_list()?,
+      ne: token.ne()?,
+      additional_info: token.additional_info()?,
+      chunk: None,
+    })
+  }
+
+  pub fn morph_features(&self, posset: CABOCHA_POSSET_TYPE) -> MorphFeatures {
+    MorphFeatures::decode(posset, &self.feature_list)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedChunk {
+  pub link: i32,
+  pub head_pos: usize,
+  pub func_pos: usize,
+  pub token_size: usize,
+  pub token_pos: usize,
+  pub score: f32,
+  pub feature_list: Vec<String>,
+  pub additional_info: String,
+}
+
+impl OwnedChunk {
+  pub fn from_chunk(chunk: &Chunk) -> Result<OwnedChunk> {
+    Ok(OwnedChunk {
+      link: chunk.link(),
+      head_pos: chunk.head_pos(),
+      func_pos: chunk.func_pos(),
+      token_size: chunk.token_size(),
+      token_pos: chunk.token_pos(),
+      score: chunk.score(),
+      feature_list: chunk.feature_list()?,
+      additional_info: chunk.additional_info()?,
+    })
+  }
+
+  pub fn features(&self) -> ChunkFeatures {
+    ChunkFeatures::parse(&self.feature_list)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedTree {
+  pub sentence: String,
+  pub charset: Option<CABOCHA_CHARSET_TYPE>,
+  pub posset: Option<CABOCHA_POSSET_TYPE>,
+  pub output_layer: Option<CABOCHA_OUTPUT>,
+  pub chunks: Vec<OwnedChunk>,
+  pub tokens: Vec<OwnedToken>,
+}
+
+impl OwnedTree {
+  pub fn from_tree(tree: &Tree) -> Result<OwnedTree> {
+    let chunks = tree
+      .chunk_iter()
+      .map(|chunk| OwnedChunk::from_chunk(&chunk))
+      .collect::<Result<Vec<_>>>()?;
+    let mut tokens = tree
+      .token_iter()
+      .map(|token| OwnedToken::from_token(&token))
+      .collect::<Result<Vec<_>>>()?;
+    for (idx, chunk) in chunks.iter().enumerate() {
+      let end = (chunk.token_pos + chunk.token_size).min(tokens.len());
+      for token in tokens.iter_mut().take(end).skip(chunk.token_pos) {
+        token.chunk = Some(idx);
+      }
+    }
+    Ok(OwnedTree {
+      sentence: tree.sentence()?,
+      charset: tree.charset(),
+      posset: tree.posset(),
+      output_layer: tree.output_layer(),
+      chunks,
+      tokens,
+    })
+  }
+
+  pub fn chunk_size(&self) -> usize {
+    self.chunks.len()
+  }
+
+  pub fn token_size(&self) -> usize {
+    self.tokens.len()
+  }
+
+  pub fn chunk_tokens(&self, index: usize) -> &[OwnedToken] {
+    match self.chunks.get(index) {
+      Some(chunk) => {
+        let start = chunk.token_pos.min(self.tokens.len());
+        let end = (chunk.token_pos + chunk.token_size).min(self.tokens.len());
+        &self.tokens[start..end]
+      }
+      None => &[],
+    }
+  }
+
+  pub fn token_byte_spans(&self) -> Result<Vec<Range<usize>>> {
+    let surfaces = self
+      .tokens
+      .iter()
+      .map(|token| token.surface.as_str())
+      .collect::<Vec<_>>();
+    align_surfaces(&self.sentence, &surfaces)
+  }
+
+  pub fn token_char_spans(&self) -> Result<Vec<Range<usize>>> {
+    Ok(to_char_spans(&self.sentence, &self.token_byte_spans()?))
+  }
+
+  pub fn named_entities(&self) -> Result<Vec<NamedEntity>> {
+    let surfaces = self
+      .tokens
+      .iter()
+      .map(|token| token.surface.as_str())
+      .collect::<Vec<_>>();
+    let tags = self
+      .tokens
+      .iter()
+      .map(|token| token.ne.as_str())
+      .collect::<Vec<_>>();
+    named_entities(&self.sentence, &surfaces, &tags)
+  }
+
+  /// Check the NE tags with `validate_iob`.
+  pub fn validate_ne(&self) -> Result<()> {
+    let tags = self
+      .tokens
+      .iter()
+      .map(|token| token.ne.as_str())
+      .collect::<Vec<_>>();
+    validate_iob(&tags)
+  }
+}
+
+impl<'a> TryFrom<&'a Tree> for OwnedTree {
+  type Error = Error;
+
+  fn try_from(tree: &'a Tree) -> Result<OwnedTree> {
+    OwnedTree::from_tree(tree)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Parser;
+  use std::thread;
+
+  fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+
+  #[test]
+  fn test_owned_tree_is_send_sync_clone() {
+    assert_send_sync_clone::<OwnedTree>();
+    assert_send_sync_clone::<OwnedChunk>();
+    assert_send_sync_clone::<OwnedToken>();
+  }
+
+  #[test]
+  fn test_owned_tree_from_tree() {
+    let mut parser = Parser::new("").unwrap();
+    let owned = {
+      let tree = parser
+        .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+        .unwrap();
+      tree.to_owned_tree().unwrap()
+    };
+
+    let owned = thread::spawn(move || owned).join().unwrap();
+    assert_eq!("一郎は二郎が描いた絵を三郎に贈った。", owned.sentence);
+    assert_eq!(Some(CABOCHA_POSSET_TYPE::IPA), owned.posset);
+    assert_eq!(6, owned.chunk_size());
+    assert_eq!(13, owned.token_size());
+
+    assert_eq!(5, owned.chunks[0].link);
+    assert_eq!(0, owned.chunks[0].token_pos);
+    assert_eq!(2, owned.chunks[0].token_size);
+    assert_eq!(-1, owned.chunks[5].link);
+    assert_eq!("FEOS:1", owned.chunks[5].feature_list[20]);
+
+    assert_eq!("一郎", owned.tokens[0].surface);
+    assert_eq!("イチロウ", owned.tokens[0].feature_list[7]);
+    assert_eq!(Some(0), owned.tokens[0].chunk);
+    assert_eq!(Some(5), owned.tokens[12].chunk);
+
+    let surfaces = owned
+      .chunk_tokens(5)
+      .iter()
+      .map(|token| token.surface.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(vec!["贈っ", "た", "。"], surfaces);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_owned_tree_serde() {
+    let owned = OwnedTree {
+      sentence: String::from("猫。"),
+      charset: Some(CABOCHA_CHARSET_TYPE::UTF8),
+      posset: Some(CABOCHA_POSSET_TYPE::IPA),
+      output_layer: Some(CABOCHA_OUTPUT::DEP),
+      chunks: vec![OwnedChunk {
+        link: -1,
+        head_pos: 0,
+        func_pos: 1,
+        token_size: 2,
+        token_pos: 0,
+        score: 0.0,
+        feature_list: vec![String::from("FEOS:1")],
+        additional_info: String::new(),
+      }],
+      tokens: vec![
+        OwnedToken {
+          surface: String::from("猫"),
+          normalized_surface: String::from("猫"),
+          feature: String::from("名詞,一般,*,*,*,*,猫,ネコ,ネコ"),
+          feature_list: "名詞,一般,*,*,*,*,猫,ネコ,ネコ"
+            .split(',')
+            .map(String::from)
+            .collect(),
+          ne: String::from("O"),
+          additional_info: String::new(),
+          chunk: Some(0),
+        },
+        OwnedToken {
+          surface: String::from("。"),
+          normalized_surface: String::from("。"),
+          feature: String::from("記号,句点,*,*,*,*,。,。,。"),
+          feature_list: "記号,句点,*,*,*,*,。,。,。"
+            .split(',')
+            .map(String::from)
+            .collect(),
+          ne: String::from("O"),
+          additional_info: String::new(),
+          chunk: Some(0),
+        },
+      ],
+    };
+    let json = serde_json::to_string(&owned).unwrap();
+    assert!(json.contains(r#""charset":"UTF8""#));
+    assert_eq!(owned, serde_json::from_str::<OwnedTree>(&json).unwrap());
+  }
+}
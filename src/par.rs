@@ -0,0 +1,100 @@
+use std::sync::{Mutex, MutexGuard};
+
+use rayon::prelude::*;
+
+use super::error::*;
+use super::owned::*;
+use super::parser::*;
+use super::tree::*;
+
+/// One value per thread of the current rayon pool.
+pub(crate) struct PerThread<T>(Vec<Mutex<T>>);
+
+impl<T> PerThread<T> {
+  pub(crate) fn new<F: Fn() -> T>(init: F) -> PerThread<T> {
+    PerThread(
+      (0..rayon::current_num_threads())
+        .map(|_| Mutex::new(init()))
+        .collect(),
+    )
+  }
+
+  pub(crate) fn get(&self) -> MutexGuard<'_, T> {
+    // each slot is only locked by its own thread, and a parse panicking
+    // midway leaves nothing that the next reparse does not reset
+    let index = rayon::current_thread_index().unwrap_or(0) % self.0.len();
+    self.0[index].lock().unwrap_or_else(|err| err.into_inner())
+  }
+}
+
+/// Parse `sentences` on the current rayon pool with parsers built from `builder`.
+///
+/// Each worker thread builds its own parser the first time it takes a sentence, so
+/// every call loads the models once per participating thread; to parse many batches,
+/// build a `ParserPool` once and use `ParserPool::par_parse` instead. A sentence that
+/// fails to parse, or whose worker fails to build a parser, yields its own `Err`.
+///
+/// Results follow the order of `sentences`; iterators without a defined order,
+/// such as `par_bridge`, give results in an unspecified order.
+pub fn par_parse<I>(builder: &ParserBuilder, sentences: I) -> Vec<Result<OwnedTree>>
+where
+  I: ParallelIterator,
+  I::Item: AsRef<str>,
+{
+  let workers = PerThread::new(|| None);
+  sentences
+    .map(|sentence| {
+      let mut worker = workers.get();
+      if worker.is_none() {
+        *worker = Some((builder.build()?, Tree::new()));
+      }
+      let (parser, tree) = worker.as_mut().unwrap();
+      parser.reparse(tree, sentence.as_ref())?;
+      tree.to_owned_tree()
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_par_parse() {
+    let sentences = (0..32)
+      .map(|i| {
+        if i % 2 == 0 {
+          String::from("一郎は二郎が描いた絵を三郎に贈った。")
+        } else {
+          String::from("猫が鳴いた。")
+        }
+      })
+      .collect::<Vec<_>>();
+    let results = par_parse(&Parser::builder(), sentences.par_iter());
+    assert_eq!(sentences.len(), results.len());
+    for (sentence, result) in sentences.iter().zip(results) {
+      assert_eq!(*sentence, result.unwrap().sentence);
+    }
+  }
+
+  #[test]
+  fn test_par_parse_unindexed() {
+    let text = "猫が鳴いた。\n犬が吠えた。\n鳥が飛んだ。";
+    let results = par_parse(&Parser::builder(), text.par_lines());
+    let sentences = results
+      .into_iter()
+      .map(|result| result.unwrap().sentence)
+      .collect::<Vec<_>>();
+    assert_eq!(text.lines().collect::<Vec<_>>(), sentences);
+  }
+
+  #[test]
+  fn test_par_parse_build_error() {
+    let builder = Parser::builder().parser_model("/no/such/model");
+    let results = par_parse(&builder, vec!["猫が鳴いた。"; 4].into_par_iter());
+    assert_eq!(4, results.len());
+    assert!(results
+      .iter()
+      .all(|result| matches!(result, Err(Error::ModelNotFound(_)))));
+  }
+}
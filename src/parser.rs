@@ -1,7 +1,12 @@
 use std::ffi::CString;
 use std::os::raw::*;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::Mutex;
 
+use super::consts::*;
+use super::error::*;
+use super::owned::*;
 use super::sys::*;
 use super::tree::*;
 use super::utils::*;
@@ -11,6 +16,16 @@ pub struct Parser {
   input: *const i8,
 }
 
+// SAFETY: a `cabocha_t` owns its own tagger, models and output buffers and keeps
+// no reference to thread-local state, so it may be used and destroyed on another
+// thread. It is not `Sync`: parsing mutates the handle, so sharing across threads
+// goes through `ParserPool`. `input` points to a buffer owned by this parser.
+unsafe impl Send for Parser {}
+
+// `cabocha_strerror(NULL)` returns a process-global message, so a failed
+// construction and the read of its error must not interleave with another one.
+static CONSTRUCTION: Mutex<()> = Mutex::new(());
+
 impl Drop for Parser {
   fn drop(&mut self) {
     self.free_input();
@@ -21,44 +36,311 @@ impl Drop for Parser {
 }
 
 impl Parser {
-  pub fn new<T: Into<Vec<u8>>>(arg: T) -> Parser {
-    Parser {
-      inner: unsafe { cabocha_new2(str_to_heap_ptr(arg)) } as *mut c_void,
-      input: ptr::null(),
+  pub fn new<T: Into<Vec<u8>>>(arg: T) -> Result<Parser> {
+    Parser::try_new(arg)
+  }
+
+  pub fn try_new<T: Into<Vec<u8>>>(arg: T) -> Result<Parser> {
+    let arg = CString::new(arg)?;
+    Parser::create(|| unsafe { cabocha_new2(arg.as_ptr()) } as *mut c_void)
+  }
+
+  pub fn builder() -> ParserBuilder {
+    ParserBuilder::new()
+  }
+
+  fn from_args(args: &[CString]) -> Result<Parser> {
+    let argv = args.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+    Parser::create(|| unsafe { cabocha_new(argv.len() as c_int, argv.as_ptr()) } as *mut c_void)
+  }
+
+  /// Wrap the handle returned by `new`, or read the error if it is null. Both happen
+  /// under `CONSTRUCTION` so concurrent failures report their own messages.
+  fn create<F: FnOnce() -> *mut c_void>(new: F) -> Result<Parser> {
+    let _guard = CONSTRUCTION.lock().unwrap_or_else(|err| err.into_inner());
+    let inner = new();
+    if inner.is_null() {
+      let message = unsafe { ptr_to_string(cabocha_strerror(ptr::null_mut())) }?;
+      return Err(Error::from_message(message));
     }
+    Ok(Parser {
+      inner,
+      input: ptr::null(),
+    })
   }
 
   fn free_input(&mut self) {
     if !self.input.is_null() {
       unsafe {
-        CString::from_raw(self.input as *mut i8);
+        drop(CString::from_raw(self.input as *mut i8));
       }
+      self.input = ptr::null();
     }
   }
 
-  pub fn parse_to_tree<T: Into<Vec<u8>>>(&mut self, text: T) -> Tree {
+  pub fn parse_to_tree<T: Into<Vec<u8>>>(&mut self, text: T) -> Result<Tree> {
     let tree_ptr = unsafe { cabocha_tree_new() } as *mut c_void;
+    if tree_ptr.is_null() {
+      return Err(Error::NullHandle);
+    }
     let mut tree = Tree::new_from_ptr(tree_ptr);
-    tree.set_sentence(text);
-    unsafe { cabocha_parse_tree(self.inner, tree.inner) };
-    tree
+    tree.set_sentence(text)?;
+    self.parse_tree(&mut tree)?;
+    Ok(tree)
+  }
+
+  pub fn parse_tree(&mut self, tree: &mut Tree) -> Result<()> {
+    if unsafe { cabocha_parse_tree(self.inner, tree.inner) }.is_null() {
+      return Err(self.parse_error());
+    }
+    Ok(())
+  }
+
+  pub fn reparse<T: Into<Vec<u8>>>(&mut self, tree: &mut Tree, text: T) -> Result<()> {
+    tree.clear();
+    tree.set_sentence(text)?;
+    self.parse_tree(tree)
+  }
+
+  pub fn parse_iter<I>(&mut self, sentences: I) -> ParseIter<'_, I::IntoIter>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    ParseIter::new(self, sentences.into_iter())
+  }
+
+  pub fn parse_batch<S: AsRef<str>>(&mut self, sentences: &[S]) -> Vec<Result<OwnedTree>> {
+    self.parse_iter(sentences).collect()
   }
 
-  pub fn parse_to_str<T: Into<Vec<u8>>>(&mut self, text: T) -> String {
+  pub fn parse_to_str<T: Into<Vec<u8>>>(&mut self, text: T) -> Result<String> {
+    let input = str_to_heap_ptr(text)?;
     self.free_input();
-    self.input = str_to_heap_ptr(text.into());
-    unsafe { ptr_to_string(cabocha_sparse_tostr(self.inner, self.input)) }
+    self.input = input;
+    let result = unsafe { cabocha_sparse_tostr(self.inner, self.input) };
+    if result.is_null() {
+      return Err(self.parse_error());
+    }
+    ptr_to_string(result)
+  }
+
+  pub fn get_last_error(&self) -> Option<Error> {
+    match unsafe { ptr_to_string(cabocha_strerror(self.inner)) } {
+      Ok(ref message) if message.is_empty() => None,
+      Ok(message) => Some(Error::Parse(message)),
+      Err(err) => Some(err),
+    }
+  }
+
+  fn parse_error(&self) -> Error {
+    self
+      .get_last_error()
+      .unwrap_or_else(|| Error::Parse(String::new()))
+  }
+}
+
+/// Parses each sentence into one reused `Tree`.
+/// As an `Iterator` it yields owned snapshots; `next_tree` borrows the tree instead.
+pub struct ParseIter<'p, I> {
+  parser: &'p mut Parser,
+  sentences: I,
+  tree: Tree,
+}
+
+impl<'p, I> ParseIter<'p, I>
+where
+  I: Iterator,
+  I::Item: AsRef<str>,
+{
+  fn new(parser: &'p mut Parser, sentences: I) -> ParseIter<'p, I> {
+    ParseIter {
+      parser,
+      sentences,
+      tree: Tree::new(),
+    }
+  }
+
+  pub fn next_tree(&mut self) -> Option<Result<&Tree>> {
+    let sentence = self.sentences.next()?;
+    Some(
+      self
+        .parser
+        .reparse(&mut self.tree, sentence.as_ref())
+        .map(move |_| &self.tree),
+    )
+  }
+}
+
+impl<'p, I> Iterator for ParseIter<'p, I>
+where
+  I: Iterator,
+  I::Item: AsRef<str>,
+{
+  type Item = Result<OwnedTree>;
+
+  fn next(&mut self) -> Option<Result<OwnedTree>> {
+    self
+      .next_tree()
+      .map(|tree| tree.and_then(|tree| tree.to_owned_tree()))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.sentences.size_hint()
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParserBuilder {
+  parser_model: Option<PathBuf>,
+  chunker_model: Option<PathBuf>,
+  ne_model: Option<PathBuf>,
+  rcfile: Option<PathBuf>,
+  mecabrc: Option<PathBuf>,
+  mecab_dicdir: Option<PathBuf>,
+  mecab_userdic: Option<PathBuf>,
+  input_layer: Option<CABOCHA_INPUT>,
+  output_layer: Option<CABOCHA_OUTPUT>,
+  output_format: Option<CABOCHA_FORMAT>,
+  charset: Option<CABOCHA_CHARSET_TYPE>,
+  posset: Option<CABOCHA_POSSET_TYPE>,
+  ne: Option<CABOCHA_NE_MODE>,
+}
+
+impl ParserBuilder {
+  pub fn new() -> ParserBuilder {
+    ParserBuilder::default()
+  }
+
+  pub fn parser_model<P: AsRef<Path>>(mut self, path: P) -> ParserBuilder {
+    self.parser_model = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  pub fn chunker_model<P: AsRef<Path>>(mut self, path: P) -> ParserBuilder {
+    self.chunker_model = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  pub fn ne_model<P: AsRef<Path>>(mut self, path: P) -> ParserBuilder {
+    self.ne_model = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  pub fn rcfile<P: AsRef<Path>>(mut self, path: P) -> ParserBuilder {
+    self.rcfile = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  pub fn mecabrc<P: AsRef<Path>>(mut self, path: P) -> ParserBuilder {
+    self.mecabrc = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  pub fn mecab_dicdir<P: AsRef<Path>>(mut self, path: P) -> ParserBuilder {
+    self.mecab_dicdir = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  pub fn mecab_userdic<P: AsRef<Path>>(mut self, path: P) -> ParserBuilder {
+    self.mecab_userdic = Some(path.as_ref().to_path_buf());
+    self
+  }
+
+  pub fn input_layer(mut self, input_layer: CABOCHA_INPUT) -> ParserBuilder {
+    self.input_layer = Some(input_layer);
+    self
+  }
+
+  pub fn output_layer(mut self, output_layer: CABOCHA_OUTPUT) -> ParserBuilder {
+    self.output_layer = Some(output_layer);
+    self
+  }
+
+  pub fn output_format(mut self, output_format: CABOCHA_FORMAT) -> ParserBuilder {
+    self.output_format = Some(output_format);
+    self
+  }
+
+  pub fn charset(mut self, charset: CABOCHA_CHARSET_TYPE) -> ParserBuilder {
+    self.charset = Some(charset);
+    self
+  }
+
+  pub fn posset(mut self, posset: CABOCHA_POSSET_TYPE) -> ParserBuilder {
+    self.posset = Some(posset);
+    self
   }
 
-  pub fn get_last_error(&self) -> String {
-    unsafe { ptr_to_string(cabocha_strerror(self.inner)) }
+  pub fn ne(mut self, ne: CABOCHA_NE_MODE) -> ParserBuilder {
+    self.ne = Some(ne);
+    self
+  }
+
+  pub fn build(&self) -> Result<Parser> {
+    let args = self
+      .args()?
+      .into_iter()
+      .map(CString::new)
+      .collect::<std::result::Result<Vec<_>, _>>()?;
+    Parser::from_args(&args)
+  }
+
+  fn args(&self) -> Result<Vec<String>> {
+    let mut args = vec![String::from("cabocha")];
+    let paths = [
+      ("--parser-model", &self.parser_model),
+      ("--chunker-model", &self.chunker_model),
+      ("--ne-model", &self.ne_model),
+      ("--rcfile", &self.rcfile),
+      ("--mecabrc", &self.mecabrc),
+      ("--mecab-dicdir", &self.mecab_dicdir),
+      ("--mecab-userdic", &self.mecab_userdic),
+    ];
+    for (name, path) in paths.iter() {
+      if let Some(path) = path {
+        let path = path
+          .to_str()
+          .ok_or_else(|| Error::BadOption(format!("{} is not UTF-8: {}", name, path.display())))?;
+        args.push(format!("{}={}", name, path));
+      }
+    }
+    if let Some(input_layer) = self.input_layer {
+      args.push(format!("--input-layer={}", input_layer as i32));
+    }
+    if let Some(output_layer) = self.output_layer {
+      args.push(format!("--output-layer={}", output_layer as i32));
+    }
+    if let Some(output_format) = self.output_format {
+      args.push(format!("--output-format={}", output_format as i32));
+    }
+    if let Some(charset) = self.charset {
+      let charset = match charset {
+        CABOCHA_CHARSET_TYPE::EUC_JP => "EUC-JP",
+        CABOCHA_CHARSET_TYPE::CP932 => "CP932",
+        CABOCHA_CHARSET_TYPE::UTF8 => "UTF8",
+        CABOCHA_CHARSET_TYPE::ASCII => "ASCII",
+      };
+      args.push(format!("--charset={}", charset));
+    }
+    if let Some(posset) = self.posset {
+      let posset = match posset {
+        CABOCHA_POSSET_TYPE::IPA => "IPA",
+        CABOCHA_POSSET_TYPE::JUMAN => "JUMAN",
+        CABOCHA_POSSET_TYPE::UNIDIC => "UNIDIC",
+      };
+      args.push(format!("--posset={}", posset));
+    }
+    if let Some(ne) = self.ne {
+      args.push(format!("--ne={}", ne as i32));
+    }
+    Ok(args)
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::consts::*;
   use std::cmp::Ordering;
 
   macro_rules! assert_eq_approx {
@@ -83,9 +365,88 @@ mod tests {
     };
   }
 
+  #[test]
+  fn test_try_new_with_missing_model() {
+    let result = Parser::try_new("-m /no/such/model");
+    assert!(matches!(result, Err(Error::ModelNotFound(_))));
+  }
+
+  #[test]
+  fn test_try_new_with_bad_option() {
+    let result = Parser::try_new("--no-such-option");
+    assert!(matches!(result, Err(Error::BadOption(_))));
+  }
+
+  #[test]
+  fn test_try_new_concurrent_errors() {
+    let handles = (0..4)
+      .map(|i| {
+        std::thread::spawn(move || {
+          let model = format!("/no/such/model{}", i);
+          match Parser::try_new(format!("-m {}", model)) {
+            Err(Error::ModelNotFound(message)) => assert!(message.contains(&model), "{}", message),
+            other => panic!("unexpected result: {:?}", other.err()),
+          }
+        })
+      })
+      .collect::<Vec<_>>();
+    for handle in handles {
+      handle.join().unwrap();
+    }
+  }
+
+  #[test]
+  fn test_try_new_with_interior_nul() {
+    let result = Parser::try_new("-m\0model");
+    assert!(matches!(result, Err(Error::InteriorNul(_))));
+  }
+
+  #[test]
+  fn test_builder_args() {
+    let builder = Parser::builder()
+      .parser_model("/path/to/dep.ipa.model")
+      .mecab_dicdir("/path/to/ipadic")
+      .input_layer(CABOCHA_INPUT::POS)
+      .output_format(CABOCHA_FORMAT::LATTICE)
+      .charset(CABOCHA_CHARSET_TYPE::UTF8)
+      .posset(CABOCHA_POSSET_TYPE::IPA)
+      .ne(CABOCHA_NE_MODE::CONSTRAINT);
+    assert_eq!(
+      vec![
+        "cabocha",
+        "--parser-model=/path/to/dep.ipa.model",
+        "--mecab-dicdir=/path/to/ipadic",
+        "--input-layer=1",
+        "--output-format=1",
+        "--charset=UTF8",
+        "--posset=IPA",
+        "--ne=1",
+      ],
+      builder.args().unwrap()
+    );
+  }
+
+  #[test]
+  fn test_builder_build() {
+    let mut parser = Parser::builder()
+      .output_format(CABOCHA_FORMAT::LATTICE)
+      .build()
+      .unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    assert_eq!(6, tree.chunk_size());
+  }
+
+  #[test]
+  fn test_builder_build_with_missing_model() {
+    let result = Parser::builder().parser_model("/no/such/model").build();
+    assert!(matches!(result, Err(Error::ModelNotFound(_))));
+  }
+
   #[test]
   fn test_parse_to_str() {
-    let mut parser = Parser::new("");
+    let mut parser = Parser::new("").unwrap();
     assert_eq!(
       r#"  一郎は---------D
     二郎が-D     |
@@ -95,14 +456,18 @@ mod tests {
           贈った。
 EOS
 "#,
-      parser.parse_to_str("一郎は二郎が描いた絵を三郎に贈った。")
+      parser
+        .parse_to_str("一郎は二郎が描いた絵を三郎に贈った。")
+        .unwrap()
     );
   }
 
   #[test]
   fn test_parse_to_tree_to_string() {
-    let mut parser = Parser::new("");
-    let tree = parser.parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。");
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
     let result = r#"* 0 5D 0/1 -1.663431
 一郎	名詞,固有名詞,人名,名,*,*,一郎,イチロウ,イチロー
 は	助詞,係助詞,*,*,*,*,は,ハ,ワ
@@ -125,15 +490,17 @@ EOS
 EOS
 "#;
 
-    assert_eq!(result, tree.to_string(CABOCHA_FORMAT::LATTICE));
+    assert_eq!(result, tree.to_string(CABOCHA_FORMAT::LATTICE).unwrap());
   }
 
   // for macro https://github.com/rust-lang/rust-clippy/issues/3900
   #[allow(clippy::cognitive_complexity)]
   #[test]
   fn test_parse_to_tree_chunks() {
-    let mut parser = Parser::new("");
-    let tree = parser.parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。");
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
     let chunks = tree.chunks();
     assert_eq!(6, chunks.len());
 
@@ -141,31 +508,31 @@ EOS
     assert_eq!(0, chunks[0].head_pos());
     assert_eq!(1, chunks[0].func_pos());
     assert_eq_approx!(-1.663_430_8, chunks[0].score(), 4.0e-7);
-    assert_eq!("", chunks[0].additional_info());
+    assert_eq!("", chunks[0].additional_info().unwrap());
 
     assert_eq!(2, chunks[1].link());
     assert_eq!(0, chunks[1].head_pos());
     assert_eq!(1, chunks[1].func_pos());
     assert_eq_approx!(0.916_559, chunks[1].score(), 4.0e-7);
-    assert_eq!("", chunks[1].additional_info());
+    assert_eq!("", chunks[1].additional_info().unwrap());
 
     assert_eq!(3, chunks[2].link());
     assert_eq!(0, chunks[2].head_pos());
     assert_eq!(1, chunks[2].func_pos());
     assert_eq_approx!(1.678_682, chunks[2].score(), 4.0e-7);
-    assert_eq!("", chunks[2].additional_info());
+    assert_eq!("", chunks[2].additional_info().unwrap());
 
     assert_eq!(5, chunks[3].link());
     assert_eq!(0, chunks[3].head_pos());
     assert_eq!(1, chunks[3].func_pos());
     assert_eq_approx!(-1.663_431, chunks[3].score(), 4.0e-7);
-    assert_eq!("", chunks[3].additional_info());
+    assert_eq!("", chunks[3].additional_info().unwrap());
 
     assert_eq!(5, chunks[4].link());
     assert_eq!(0, chunks[4].head_pos());
     assert_eq!(1, chunks[4].func_pos());
     assert_eq_approx!(-1.663_431, chunks[4].score(), 4.0e-7);
-    assert_eq!("", chunks[4].additional_info());
+    assert_eq!("", chunks[4].additional_info().unwrap());
 
     assert_eq!(-1, chunks[5].link());
     assert_eq!(0, chunks[5].head_pos());
@@ -196,28 +563,35 @@ EOS
         "FEOS:1",
         "A:基本形"
       ],
-      chunks[5].feature_list()
+      chunks[5].feature_list().unwrap()
     );
     assert_eq!(22, chunks[5].feature_list_size());
-    assert_eq!("", chunks[5].additional_info());
+    assert_eq!("", chunks[5].additional_info().unwrap());
   }
 
   #[test]
   fn test_parse_to_tree_sentence() {
-    let mut parser = Parser::new("");
-    let tree = parser.parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。");
-    assert_eq!("一郎は二郎が描いた絵を三郎に贈った。", tree.sentence());
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    assert_eq!(
+      "一郎は二郎が描いた絵を三郎に贈った。",
+      tree.sentence().unwrap()
+    );
   }
 
   #[test]
   fn test_parse_to_tree_tokens() {
-    let mut parser = Parser::new("");
-    let tree = parser.parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。");
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
     let tokens = tree.tokens();
     assert_eq!(13, tokens.len());
 
-    assert_eq!("一郎", tokens[0].surface());
-    assert_eq!("一郎", tokens[0].normalized_surface());
+    assert_eq!("一郎", tokens[0].surface().unwrap());
+    assert_eq!("一郎", tokens[0].normalized_surface().unwrap());
     let feature_list = vec![
       "名詞",
       "固有名詞",
@@ -229,23 +603,23 @@ EOS
       "イチロウ",
       "イチロー",
     ];
-    assert_eq!(feature_list.join(","), tokens[0].feature());
-    assert_eq!(feature_list, tokens[0].feature_list());
+    assert_eq!(feature_list.join(","), tokens[0].feature().unwrap());
+    assert_eq!(feature_list, tokens[0].feature_list().unwrap());
     assert_eq!(9, tokens[0].feature_list_size());
-    assert_eq!("", tokens[0].ne());
-    assert_eq!("", tokens[0].additional_info());
+    assert_eq!("", tokens[0].ne().unwrap());
+    assert_eq!("", tokens[0].additional_info().unwrap());
 
-    assert_eq!("は", tokens[1].surface());
-    assert_eq!("は", tokens[1].normalized_surface());
+    assert_eq!("は", tokens[1].surface().unwrap());
+    assert_eq!("は", tokens[1].normalized_surface().unwrap());
     let feature_list = vec!["助詞", "係助詞", "*", "*", "*", "*", "は", "ハ", "ワ"];
-    assert_eq!(feature_list.join(","), tokens[1].feature());
-    assert_eq!(feature_list, tokens[1].feature_list());
+    assert_eq!(feature_list.join(","), tokens[1].feature().unwrap());
+    assert_eq!(feature_list, tokens[1].feature_list().unwrap());
     assert_eq!(9, tokens[1].feature_list_size());
-    assert_eq!("", tokens[1].ne());
-    assert_eq!("", tokens[1].additional_info());
+    assert_eq!("", tokens[1].ne().unwrap());
+    assert_eq!("", tokens[1].additional_info().unwrap());
 
-    assert_eq!("二郎", tokens[2].surface());
-    assert_eq!("二郎", tokens[2].normalized_surface());
+    assert_eq!("二郎", tokens[2].surface().unwrap());
+    assert_eq!("二郎", tokens[2].normalized_surface().unwrap());
     let feature_list = vec![
       "名詞",
       "固有名詞",
@@ -257,10 +631,109 @@ EOS
       "ジロウ",
       "ジロー",
     ];
-    assert_eq!(feature_list.join(","), tokens[2].feature());
-    assert_eq!(feature_list, tokens[2].feature_list());
+    assert_eq!(feature_list.join(","), tokens[2].feature().unwrap());
+    assert_eq!(feature_list, tokens[2].feature_list().unwrap());
     assert_eq!(9, tokens[2].feature_list_size());
-    assert_eq!("", tokens[2].ne());
-    assert_eq!("", tokens[2].additional_info());
+    assert_eq!("", tokens[2].ne().unwrap());
+    assert_eq!("", tokens[2].additional_info().unwrap());
+  }
+
+  #[test]
+  fn test_parse_to_tree_str_accessors() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    assert_eq!(
+      "一郎は二郎が描いた絵を三郎に贈った。",
+      tree.sentence_str().unwrap()
+    );
+
+    let token = tree.token(0).unwrap();
+    assert_eq!("一郎", token.surface_str().unwrap());
+    assert_eq!("一郎", token.normalized_surface_str().unwrap());
+    assert_eq!(
+      "名詞,固有名詞,人名,名,*,*,一郎,イチロウ,イチロー",
+      token.feature_str().unwrap()
+    );
+    assert_eq!("", token.ne_str().unwrap());
+    assert_eq!("", token.additional_info_str().unwrap());
+    let features = token.feature_iter();
+    assert_eq!(9, features.len());
+    assert_eq!(
+      vec![
+        "名詞",
+        "固有名詞",
+        "人名",
+        "名",
+        "*",
+        "*",
+        "一郎",
+        "イチロウ",
+        "イチロー"
+      ],
+      features.collect::<Result<Vec<_>>>().unwrap()
+    );
+
+    let chunk = tree.chunk(5).unwrap();
+    assert_eq!(
+      Some("FEOS:1"),
+      chunk.feature_iter().nth(20).transpose().unwrap()
+    );
+    assert_eq!("", chunk.additional_info_str().unwrap());
+  }
+
+  #[test]
+  fn test_parse_tree() {
+    let mut parser = Parser::new("").unwrap();
+    let mut tree = Tree::new();
+    tree
+      .set_sentence("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    parser.parse_tree(&mut tree).unwrap();
+    assert_eq!(6, tree.chunk_size());
+    assert_eq!(13, tree.token_size());
+  }
+
+  #[test]
+  fn test_reparse() {
+    let mut parser = Parser::new("").unwrap();
+    let mut tree = Tree::new();
+    parser
+      .reparse(&mut tree, "一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    assert_eq!(6, tree.chunk_size());
+
+    parser.reparse(&mut tree, "猫が鳴いた。").unwrap();
+    assert_eq!("猫が鳴いた。", tree.sentence_str().unwrap());
+    assert_eq!(2, tree.chunk_size());
+    assert_eq!("猫", tree.token(0).unwrap().surface_str().unwrap());
+  }
+
+  #[test]
+  fn test_parse_batch() {
+    let mut parser = Parser::new("").unwrap();
+    let results = parser.parse_batch(&[
+      "一郎は二郎が描いた絵を三郎に贈った。",
+      "猫\0が",
+      "猫が鳴いた。",
+    ]);
+    assert_eq!(3, results.len());
+    assert_eq!(6, results[0].as_ref().unwrap().chunk_size());
+    assert!(matches!(results[1], Err(Error::InteriorNul(_))));
+    assert_eq!("猫が鳴いた。", results[2].as_ref().unwrap().sentence);
+  }
+
+  #[test]
+  fn test_parse_iter_next_tree() {
+    let mut parser = Parser::new("").unwrap();
+    let sentences = vec![String::from("猫が鳴いた。"), String::from("犬が吠えた。")];
+    let mut iter = parser.parse_iter(&sentences);
+    let mut surfaces = Vec::new();
+    while let Some(tree) = iter.next_tree() {
+      let tree = tree.unwrap();
+      surfaces.push(tree.token(0).unwrap().surface().unwrap());
+    }
+    assert_eq!(vec!["猫", "犬"], surfaces);
   }
 }
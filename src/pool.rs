@@ -0,0 +1,292 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[cfg(feature = "rayon")]
+use super::consts::*;
+use super::error::*;
+#[cfg(feature = "rayon")]
+use super::owned::*;
+#[cfg(feature = "rayon")]
+use super::par::*;
+use super::parser::*;
+#[cfg(feature = "rayon")]
+use super::tree::*;
+
+/// A fixed set of independently created parsers shared between threads.
+///
+/// Each cabocha parser owns its own model and MeCab tagger, so a `Parser` (and a `Tree`)
+/// may be moved to another thread, but must never be used from two threads at once.
+/// The pool enforces this by lending each parser to one caller at a time; the lock is
+/// only held while a parser is checked out or returned, never while parsing.
+pub struct ParserPool {
+  parsers: Mutex<Vec<Parser>>,
+  available: Condvar,
+  size: usize,
+}
+
+impl ParserPool {
+  pub fn new(builder: &ParserBuilder, size: usize) -> Result<ParserPool> {
+    if size == 0 {
+      return Err(Error::InvalidInput(String::from(
+        "pool size must be positive",
+      )));
+    }
+    let parsers = (0..size)
+      .map(|_| builder.build())
+      .collect::<Result<Vec<_>>>()?;
+    ParserPool::from_parsers(parsers)
+  }
+
+  pub fn from_parsers(parsers: Vec<Parser>) -> Result<ParserPool> {
+    if parsers.is_empty() {
+      return Err(Error::InvalidInput(String::from(
+        "pool size must be positive",
+      )));
+    }
+    Ok(ParserPool {
+      size: parsers.len(),
+      parsers: Mutex::new(parsers),
+      available: Condvar::new(),
+    })
+  }
+
+  pub fn size(&self) -> usize {
+    self.size
+  }
+
+  /// Blocks until a parser is free.
+  pub fn get(&self) -> PooledParser<'_> {
+    let mut parsers = self.lock();
+    loop {
+      if let Some(parser) = parsers.pop() {
+        return PooledParser::new(self, parser);
+      }
+      parsers = self
+        .available
+        .wait(parsers)
+        .unwrap_or_else(|err| err.into_inner());
+    }
+  }
+
+  pub fn try_get(&self) -> Option<PooledParser<'_>> {
+    self
+      .lock()
+      .pop()
+      .map(|parser| PooledParser::new(self, parser))
+  }
+
+  fn lock(&self) -> MutexGuard<'_, Vec<Parser>> {
+    // the lock never guards a parse, so a poisoned vec is still consistent
+    self.parsers.lock().unwrap_or_else(|err| err.into_inner())
+  }
+
+  fn release(&self, parser: Parser) {
+    self.lock().push(parser);
+    self.available.notify_one();
+  }
+}
+
+#[cfg(feature = "rayon")]
+impl ParserPool {
+  /// Like `cabocha::par_parse`, reusing the parsers of this pool.
+  /// A parser is borrowed for one sentence at a time, so workers beyond
+  /// the pool size wait for a free parser but never hold one between sentences.
+  pub fn par_parse<I>(&self, sentences: I) -> Vec<Result<OwnedTree>>
+  where
+    I: ParallelIterator,
+    I::Item: AsRef<str>,
+  {
+    self.par_parse_with(sentences, |parser, tree, sentence| {
+      parser.reparse(tree, sentence)
+    })
+  }
+
+  /// Like `par_parse` for `EOS`-terminated lattice blocks, each loaded with
+  /// `Tree::read_str` at `input_layer`. The parsers should be built with the
+  /// same `input_layer` so that they only run the layers above it.
+  pub fn par_parse_lattice<I>(
+    &self,
+    lattices: I,
+    input_layer: CABOCHA_INPUT,
+  ) -> Vec<Result<OwnedTree>>
+  where
+    I: ParallelIterator,
+    I::Item: AsRef<str>,
+  {
+    self.par_parse_with(lattices, |parser, tree, lattice| {
+      tree.clear();
+      tree.set_charset(CABOCHA_CHARSET_TYPE::UTF8);
+      tree.read_str(lattice, input_layer)?;
+      parser.parse_tree(tree)
+    })
+  }
+
+  fn par_parse_with<I, F>(&self, inputs: I, parse: F) -> Vec<Result<OwnedTree>>
+  where
+    I: ParallelIterator,
+    I::Item: AsRef<str>,
+    F: Fn(&mut Parser, &mut Tree, &str) -> Result<()> + Sync,
+  {
+    let trees = PerThread::new(Tree::new);
+    inputs
+      .map(|input| {
+        let mut tree = trees.get();
+        parse(&mut self.get(), &mut tree, input.as_ref())?;
+        tree.to_owned_tree()
+      })
+      .collect()
+  }
+}
+
+/// A parser checked out of a `ParserPool`, returned to it on drop.
+pub struct PooledParser<'pool> {
+  pool: &'pool ParserPool,
+  parser: Option<Parser>,
+}
+
+impl<'pool> PooledParser<'pool> {
+  fn new(pool: &'pool ParserPool, parser: Parser) -> PooledParser<'pool> {
+    PooledParser {
+      pool,
+      parser: Some(parser),
+    }
+  }
+}
+
+impl<'pool> Deref for PooledParser<'pool> {
+  type Target = Parser;
+
+  fn deref(&self) -> &Parser {
+    self.parser.as_ref().unwrap()
+  }
+}
+
+impl<'pool> DerefMut for PooledParser<'pool> {
+  fn deref_mut(&mut self) -> &mut Parser {
+    self.parser.as_mut().unwrap()
+  }
+}
+
+impl<'pool> Drop for PooledParser<'pool> {
+  fn drop(&mut self) {
+    if let Some(parser) = self.parser.take() {
+      self.pool.release(parser);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tree::Tree;
+  use std::sync::Arc;
+  use std::thread;
+
+  fn assert_send<T: Send>() {}
+  fn assert_send_sync<T: Send + Sync>() {}
+
+  #[test]
+  fn test_pool_is_send_sync() {
+    assert_send::<Parser>();
+    assert_send::<Tree>();
+    assert_send_sync::<ParserPool>();
+  }
+
+  #[test]
+  fn test_pool_empty() {
+    assert!(matches!(
+      ParserPool::new(&Parser::builder(), 0),
+      Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+      ParserPool::from_parsers(Vec::new()),
+      Err(Error::InvalidInput(_))
+    ));
+  }
+
+  #[test]
+  fn test_pool_get() {
+    let pool = ParserPool::new(&Parser::builder(), 2).unwrap();
+    assert_eq!(2, pool.size());
+    {
+      let _first = pool.get();
+      let _second = pool.get();
+      assert!(pool.try_get().is_none());
+    }
+    assert!(pool.try_get().is_some());
+  }
+
+  #[test]
+  fn test_pool_threads() {
+    let pool = Arc::new(ParserPool::new(&Parser::builder(), 2).unwrap());
+    let handles = (0..4)
+      .map(|_| {
+        let pool = Arc::clone(&pool);
+        thread::spawn(move || {
+          let mut parser = pool.get();
+          parser
+            .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+            .unwrap()
+            .chunk_size()
+        })
+      })
+      .collect::<Vec<_>>();
+    for handle in handles {
+      assert_eq!(6, handle.join().unwrap());
+    }
+  }
+
+  #[cfg(feature = "rayon")]
+  #[test]
+  fn test_par_parse_errors_in_place() {
+    let pool = ParserPool::new(&Parser::builder(), 2).unwrap();
+    let results = pool.par_parse(vec!["猫が鳴いた。", "猫\0が", "犬が吠えた。"].into_par_iter());
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(Error::InteriorNul(_))));
+    assert_eq!("犬が吠えた。", results[2].as_ref().unwrap().sentence);
+  }
+
+  #[cfg(feature = "rayon")]
+  #[test]
+  fn test_par_parse_lattice() {
+    let builder = Parser::builder().input_layer(CABOCHA_INPUT::POS);
+    let pool = ParserPool::new(&builder, 2).unwrap();
+    let lattice = "猫\t名詞,一般,*,*,*,*,猫,ネコ,ネコ
+が\t助詞,格助詞,一般,*,*,*,が,ガ,ガ
+鳴い\t動詞,自立,*,*,五段・カ行イ音便,連用タ接続,鳴く,ナイ,ナイ
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ
+EOS
+";
+    let results = pool.par_parse_lattice(vec![lattice; 4].into_par_iter(), CABOCHA_INPUT::POS);
+    for result in results {
+      let tree = result.unwrap();
+      assert_eq!("猫が鳴いた", tree.sentence);
+      assert_eq!(2, tree.chunk_size());
+      assert_eq!(1, tree.chunks[0].link);
+    }
+  }
+
+  #[cfg(feature = "rayon")]
+  #[test]
+  fn test_par_parse_small_pool_nested() {
+    let threads = rayon::ThreadPoolBuilder::new()
+      .num_threads(4)
+      .build()
+      .unwrap();
+    let pool = ParserPool::new(&Parser::builder(), 1).unwrap();
+    let sentences = vec!["猫が鳴いた。"; 16];
+    let results = threads.install(|| {
+      (0..4)
+        .into_par_iter()
+        .map(|_| pool.par_parse(sentences.par_iter()))
+        .collect::<Vec<_>>()
+    });
+    for results in results {
+      assert_eq!(16, results.len());
+      assert!(results.into_iter().all(|result| result.is_ok()));
+    }
+  }
+}
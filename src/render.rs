@@ -0,0 +1,275 @@
+use std::fmt::Write;
+
+use super::error::*;
+use super::owned::*;
+use super::tree::*;
+use super::writer::*;
+use super::xml::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DotOptions {
+  /// Draw each chunk as a cluster of its tokens instead of a single node.
+  pub tokens: bool,
+}
+
+fn escape_dot(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+fn edge_weight(score: f32) -> u32 {
+  ((score + 1.0).max(0.0) * 10.0).round() as u32
+}
+
+impl OwnedTree {
+  pub fn to_dot(&self) -> String {
+    self.to_dot_with(&DotOptions::default())
+  }
+
+  /// Graphviz digraph with an edge from each chunk to the chunk it links to,
+  /// labeled with its score. Graphviz wants weights to be non-negative integers,
+  /// so the edge weight is the score shifted by 1, clamped at 0 and scaled by 10.
+  pub fn to_dot_with(&self, options: &DotOptions) -> String {
+    let mut dot = String::new();
+    dot.push_str("digraph tree {\n  rankdir=BT;\n  node [shape=box];\n");
+    if options.tokens {
+      dot.push_str("  compound=true;\n");
+    }
+    // edges start and end at the head token when chunks are drawn as clusters
+    let node = |index: usize| {
+      let chunk = &self.chunks[index];
+      if options.tokens {
+        format!(
+          "t{}",
+          chunk.token_pos + chunk.head_pos.min(chunk.token_size.max(1) - 1)
+        )
+      } else {
+        format!("c{}", index)
+      }
+    };
+    for index in 0..self.chunk_size() {
+      let surface = self
+        .chunk_tokens(index)
+        .iter()
+        .map(|token| token.surface.as_str())
+        .collect::<String>();
+      if options.tokens {
+        writeln!(
+          dot,
+          "  subgraph cluster_c{} {{\n    label=\"{}\";",
+          index,
+          escape_dot(&surface)
+        )
+        .unwrap();
+        let token_pos = self.chunks[index].token_pos;
+        for (offset, token) in self.chunk_tokens(index).iter().enumerate() {
+          writeln!(
+            dot,
+            "    t{} [label=\"{}\"];",
+            token_pos + offset,
+            escape_dot(&token.surface)
+          )
+          .unwrap();
+        }
+        dot.push_str("  }\n");
+      } else {
+        writeln!(dot, "  c{} [label=\"{}\"];", index, escape_dot(&surface)).unwrap();
+      }
+    }
+    for (index, chunk) in self.chunks.iter().enumerate() {
+      if chunk.link < 0 || chunk.link as usize >= self.chunk_size() {
+        continue;
+      }
+      let link = chunk.link as usize;
+      write!(
+        dot,
+        "  {} -> {} [label=\"{:.3}\", weight={}",
+        node(index),
+        node(link),
+        chunk.score,
+        edge_weight(chunk.score)
+      )
+      .unwrap();
+      if options.tokens {
+        write!(dot, ", ltail=cluster_c{}, lhead=cluster_c{}", index, link).unwrap();
+      }
+      dot.push_str("];\n");
+    }
+    dot.push_str("}\n");
+    dot
+  }
+
+  /// Standalone SVG arc diagram: chunks left to right with an arc
+  /// from each chunk to its head, labeled with the score.
+  pub fn to_svg(&self) -> String {
+    const FONT_SIZE: usize = 16;
+    const COLUMN: usize = FONT_SIZE / 2;
+    const GAP: usize = 24;
+    const ARC_STEP: usize = 24;
+
+    let surfaces = (0..self.chunk_size())
+      .map(|index| {
+        self
+          .chunk_tokens(index)
+          .iter()
+          .map(|token| token.surface.as_str())
+          .collect::<String>()
+      })
+      .collect::<Vec<_>>();
+    let mut centers = Vec::with_capacity(surfaces.len());
+    let mut x = GAP;
+    for surface in surfaces.iter() {
+      let width = width(surface) * COLUMN;
+      centers.push(x + width / 2);
+      x += width + GAP;
+    }
+    let links = self
+      .chunks
+      .iter()
+      .enumerate()
+      .filter(|(_, chunk)| chunk.link >= 0 && (chunk.link as usize) < surfaces.len())
+      .map(|(index, chunk)| (index, chunk.link as usize, chunk.score))
+      .collect::<Vec<_>>();
+    let max_distance = links
+      .iter()
+      .map(|(from, to, _)| from.abs_diff(*to))
+      .max()
+      .unwrap_or(0);
+    let baseline = ARC_STEP * (max_distance + 1) + FONT_SIZE;
+    let (width, height) = (x, baseline + FONT_SIZE);
+
+    let mut svg = String::new();
+    writeln!(
+      svg,
+      "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" font-size=\"{}\">",
+      width, height, width, height, FONT_SIZE
+    )
+    .unwrap();
+    svg.push_str("  <defs>\n    <marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\">\n      <path d=\"M0,0 L10,5 L0,10 z\"/>\n    </marker>\n  </defs>\n");
+    for (index, surface) in surfaces.iter().enumerate() {
+      writeln!(
+        svg,
+        "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+        centers[index],
+        baseline,
+        escape_xml(surface)
+      )
+      .unwrap();
+    }
+    let top = baseline - FONT_SIZE;
+    for (from, to, score) in links {
+      let distance = from.abs_diff(to);
+      let arc = top - ARC_STEP * distance;
+      let (x1, x2) = (centers[from], centers[to]);
+      writeln!(
+        svg,
+        "  <path d=\"M{} {} C{} {} {} {} {} {}\" fill=\"none\" stroke=\"black\" marker-end=\"url(#arrow)\"/>",
+        x1, top, x1, arc, x2, arc, x2, top
+      )
+      .unwrap();
+      writeln!(
+        svg,
+        "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-size=\"{}\">{:.3}</text>",
+        (x1 + x2) / 2,
+        top - ARC_STEP * distance * 3 / 4 - 2,
+        FONT_SIZE * 5 / 8,
+        score
+      )
+      .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+  }
+}
+
+impl Tree {
+  pub fn to_dot(&self) -> Result<String> {
+    Ok(self.to_owned_tree()?.to_dot())
+  }
+
+  pub fn to_dot_with(&self, options: &DotOptions) -> Result<String> {
+    Ok(self.to_owned_tree()?.to_dot_with(options))
+  }
+
+  pub fn to_svg(&self) -> Result<String> {
+    Ok(self.to_owned_tree()?.to_svg())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::*;
+  use crate::parser::Parser;
+
+  fn tree() -> OwnedTree {
+    from_lattice(
+      "* 0 1D 0/1 -0.500000
+\"猫\"\t名詞,一般,*,*,*,*,*
+が\t助詞,格助詞,一般,*,*,*,が,ガ,ガ
+* 1 -1D 0/1 0.000000
+鳴い\t動詞,自立,*,*,五段・カ行イ音便,連用タ接続,鳴く,ナイ,ナイ
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ
+EOS
+",
+    )
+  }
+
+  #[test]
+  fn test_to_dot() {
+    assert_eq!(
+      r#"digraph tree {
+  rankdir=BT;
+  node [shape=box];
+  c0 [label="\"猫\"が"];
+  c1 [label="鳴いた"];
+  c0 -> c1 [label="-0.500", weight=5];
+}
+"#,
+      tree().to_dot()
+    );
+  }
+
+  #[test]
+  fn test_to_dot_with_tokens() {
+    let dot = tree().to_dot_with(&DotOptions { tokens: true });
+    assert!(dot.contains("compound=true;\n"));
+    assert!(dot.contains("  subgraph cluster_c1 {\n    label=\"鳴いた\";\n    t2 [label=\"鳴い\"];\n    t3 [label=\"た\"];\n  }\n"));
+    assert!(dot
+      .contains("  t0 -> t2 [label=\"-0.500\", weight=5, ltail=cluster_c0, lhead=cluster_c1];\n"));
+  }
+
+  #[test]
+  fn test_edge_weight() {
+    assert_eq!(0, edge_weight(-3.2));
+    assert_eq!(0, edge_weight(-1.0));
+    assert_eq!(5, edge_weight(-0.5));
+    assert_eq!(10, edge_weight(0.0));
+    assert_eq!(27, edge_weight(1.678_682));
+  }
+
+  #[test]
+  fn test_to_svg() {
+    let svg = tree().to_svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(svg.contains(">&quot;猫&quot;が</text>"));
+    assert!(svg.contains(">鳴いた</text>"));
+    assert_eq!(1, svg.matches("marker-end=\"url(#arrow)\"").count());
+    assert!(svg.contains(">-0.500</text>"));
+  }
+
+  #[test]
+  fn test_tree_to_dot() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    let dot = tree.to_dot().unwrap();
+    assert!(dot.contains("  c0 [label=\"一郎は\"];\n"));
+    assert_eq!(5, dot.matches(" -> ").count());
+    assert_eq!(5, tree.to_svg().unwrap().matches("<path d=\"M").count());
+  }
+}
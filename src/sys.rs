@@ -33,7 +33,6 @@ extern "C" {
   pub fn cabocha_do(argc: c_int, argv: *const *const c_char) -> c_int;
 
   /* parser */
-  #[allow(dead_code)]
   pub fn cabocha_new(argc: c_int, argv: *const *const c_char) -> *const c_void;
   pub fn cabocha_new2(arg: *const c_char) -> *const c_void;
   pub fn cabocha_strerror(cabocha: *mut c_void) -> *const c_char;
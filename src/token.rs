@@ -1,31 +1,91 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::os::raw::*;
+
 use super::chunk::*;
+use super::consts::*;
+use super::error::*;
+use super::morph::*;
 use super::sys::*;
 use super::tree::*;
 use super::utils::*;
 
-#[derive(Debug, Clone)]
-pub struct Token {
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'tree> {
+  tree: &'tree Tree,
+  index: usize,
   self_ptr: *const cabocha_token_t,
 }
 
-impl Token {
-  pub fn new(raw_ptr: *const cabocha_token_t) -> Token {
-    Token { self_ptr: raw_ptr }
+impl<'tree> Token<'tree> {
+  pub(crate) fn new(
+    tree: &'tree Tree,
+    index: usize,
+    raw_ptr: *const cabocha_token_t,
+  ) -> Token<'tree> {
+    Token {
+      tree,
+      index,
+      self_ptr: raw_ptr,
+    }
+  }
+
+  pub fn tree(&self) -> &'tree Tree {
+    self.tree
+  }
+
+  pub fn index(&self) -> usize {
+    self.index
+  }
+
+  pub fn chunk_index(&self) -> Option<usize> {
+    self
+      .tree
+      .chunk_iter()
+      .find(|chunk| {
+        chunk.token_pos() <= self.index && self.index < chunk.token_pos() + chunk.token_size()
+      })
+      .map(|chunk| chunk.index())
+  }
+
+  /// Aligns the surfaces of this and all earlier tokens on each call; use
+  /// `Tree::token_byte_spans` to get the spans of every token at once.
+  pub fn byte_span(&self) -> Result<Range<usize>> {
+    let spans = self.tree.token_byte_spans_until(self.index + 1)?;
+    spans.last().cloned().ok_or(Error::Alignment(self.index))
+  }
+
+  /// Like `byte_span`, in chars; see `Tree::token_char_spans` for the bulk API.
+  pub fn char_span(&self) -> Result<Range<usize>> {
+    let sentence = self.tree.sentence_str()?;
+    Ok(to_char_span(sentence, &self.byte_span()?))
   }
 
-  pub fn surface(&self) -> String {
-    ptr_to_string(unsafe { (*self.self_ptr).surface })
+  pub fn surface(&self) -> Result<String> {
+    self.surface_str().map(str::to_string)
   }
 
-  pub fn normalized_surface(&self) -> String {
-    ptr_to_string(unsafe { (*self.self_ptr).normalized_surface })
+  pub fn surface_str(&self) -> Result<&'tree str> {
+    ptr_to_str(unsafe { (*self.self_ptr).surface })
   }
 
-  pub fn feature(&self) -> String {
-    ptr_to_string(unsafe { (*self.self_ptr).feature })
+  pub fn normalized_surface(&self) -> Result<String> {
+    self.normalized_surface_str().map(str::to_string)
   }
 
-  pub fn feature_list(&self) -> Vec<String> {
+  pub fn normalized_surface_str(&self) -> Result<&'tree str> {
+    ptr_to_str(unsafe { (*self.self_ptr).normalized_surface })
+  }
+
+  pub fn feature(&self) -> Result<String> {
+    self.feature_str().map(str::to_string)
+  }
+
+  pub fn feature_str(&self) -> Result<&'tree str> {
+    ptr_to_str(unsafe { (*self.self_ptr).feature })
+  }
+
+  pub fn feature_list(&self) -> Result<Vec<String>> {
     unsafe {
       ptr_to_vec_string(
         (*self.self_ptr).feature_list,
@@ -34,19 +94,42 @@ impl Token {
     }
   }
 
+  pub fn feature_iter(&self) -> FeatureIter<'tree> {
+    unsafe {
+      FeatureIter::new(
+        (*self.self_ptr).feature_list,
+        (*self.self_ptr).feature_list_size as usize,
+      )
+    }
+  }
+
+  pub fn morph_features(&self) -> Result<MorphFeatures> {
+    let posset = self.tree.posset().unwrap_or(CABOCHA_POSSET_TYPE::IPA);
+    let features = self.feature_iter().collect::<Result<Vec<_>>>()?;
+    Ok(MorphFeatures::decode(posset, &features))
+  }
+
   pub fn feature_list_size(&self) -> u16 {
     unsafe { (*self.self_ptr).feature_list_size }
   }
 
-  pub fn ne(&self) -> String {
-    ptr_to_string(unsafe { (*self.self_ptr).ne })
+  pub fn ne(&self) -> Result<String> {
+    self.ne_str().map(str::to_string)
+  }
+
+  pub fn ne_str(&self) -> Result<&'tree str> {
+    ptr_to_str(unsafe { (*self.self_ptr).ne })
+  }
+
+  pub fn additional_info(&self) -> Result<String> {
+    self.additional_info_str().map(str::to_string)
   }
 
-  pub fn additional_info(&self) -> String {
-    ptr_to_string(unsafe { (*self.self_ptr).additional_info })
+  pub fn additional_info_str(&self) -> Result<&'tree str> {
+    ptr_to_str(unsafe { (*self.self_ptr).additional_info })
   }
 
-  pub fn chunk(&self) -> Option<Chunk> {
+  pub fn chunk(&self) -> Option<Chunk<'tree>> {
     if self.self_ptr.is_null() {
       None
     } else {
@@ -54,7 +137,12 @@ impl Token {
       if chunk_ptr.is_null() {
         None
       } else {
-        Some(Chunk::new(chunk_ptr))
+        (0..self.tree.chunk_size())
+          .find(|&index| {
+            let raw_ptr = unsafe { cabocha_tree_chunk(self.tree.inner, index) };
+            std::ptr::eq(raw_ptr, chunk_ptr)
+          })
+          .map(|index| Chunk::new(self.tree, index, chunk_ptr))
       }
     }
   }
@@ -66,9 +154,9 @@ pub struct TokenIter<'a> {
 }
 
 impl<'a> Iterator for TokenIter<'a> {
-  type Item = Token;
+  type Item = Token<'a>;
 
-  fn next(&mut self) -> Option<Token> {
+  fn next(&mut self) -> Option<Token<'a>> {
     let token = self.tree.token(self.pos);
     self.pos += 1;
     token
@@ -76,7 +164,46 @@ impl<'a> Iterator for TokenIter<'a> {
 }
 
 impl<'a> TokenIter<'a> {
-  pub fn new(tree: &Tree) -> TokenIter {
+  pub fn new(tree: &'a Tree) -> TokenIter<'a> {
     TokenIter { tree, pos: 0 }
   }
 }
+
+pub struct FeatureIter<'tree> {
+  ptr: *const *const c_char,
+  size: usize,
+  pos: usize,
+  tree: PhantomData<&'tree Tree>,
+}
+
+impl<'tree> Iterator for FeatureIter<'tree> {
+  type Item = Result<&'tree str>;
+
+  fn next(&mut self) -> Option<Result<&'tree str>> {
+    if self.pos < self.size {
+      let feature = ptr_to_str(unsafe { *self.ptr.add(self.pos) });
+      self.pos += 1;
+      Some(feature)
+    } else {
+      None
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = self.size - self.pos;
+    (remaining, Some(remaining))
+  }
+}
+
+impl<'tree> ExactSizeIterator for FeatureIter<'tree> {}
+
+impl<'tree> FeatureIter<'tree> {
+  pub(crate) fn new(ptr: *const *const c_char, size: usize) -> FeatureIter<'tree> {
+    FeatureIter {
+      ptr,
+      size: if ptr.is_null() { 0 } else { size },
+      pos: 0,
+      tree: PhantomData,
+    }
+  }
+}
@@ -1,18 +1,36 @@
 use std::ffi::CString;
+use std::fmt::{self, Write};
+use std::ops::Range;
 use std::os::raw::*;
-use std::ptr;
+use std::{ptr, slice, str};
 
 use super::chunk::*;
 use super::consts::*;
+use super::error::*;
+use super::morph::*;
+use super::ne::*;
+use super::owned::*;
+use super::parser::*;
 use super::sys::*;
 use super::token::*;
 use super::utils::*;
 
 pub struct Tree {
-  pub inner: *mut c_void,
+  pub(crate) inner: *mut c_void,
   input: *const i8,
 }
 
+impl fmt::Debug for Tree {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Tree").field("inner", &self.inner).finish()
+  }
+}
+
+// SAFETY: a `cabocha_tree_t` owns its tokens, chunks and string pool and is
+// only mutated through `&mut Tree`, so it may move to another thread. Borrowed
+// `Token`/`Chunk` views keep it from moving while they are alive.
+unsafe impl Send for Tree {}
+
 impl Drop for Tree {
   fn drop(&mut self) {
     self.free_input();
@@ -29,18 +47,19 @@ impl Default for Tree {
 }
 
 impl Tree {
-  pub fn new_from_ptr(inner: *mut c_void) -> Tree {
+  pub(crate) fn new_from_ptr(inner: *mut c_void) -> Tree {
     Tree {
       inner,
       input: ptr::null(),
     }
   }
 
-  fn free_input(&self) {
+  fn free_input(&mut self) {
     if !self.input.is_null() {
       unsafe {
-        CString::from_raw(self.input as *mut i8);
+        drop(CString::from_raw(self.input as *mut i8));
       }
+      self.input = ptr::null();
     }
   }
 
@@ -51,85 +70,153 @@ impl Tree {
     }
   }
 
-  pub fn sentence(&self) -> String {
-    unsafe { ptr_to_string(cabocha_tree_sentence(self.inner)) }
+  pub fn sentence(&self) -> Result<String> {
+    self.sentence_str().map(str::to_string)
+  }
+
+  pub fn sentence_str(&self) -> Result<&str> {
+    let ptr = unsafe { cabocha_tree_sentence(self.inner) };
+    if ptr.is_null() {
+      return Ok("");
+    }
+    let bytes = unsafe { slice::from_raw_parts(ptr as *const u8, self.sentence_size()) };
+    Ok(str::from_utf8(bytes)?)
   }
 
   pub fn sentence_size(&self) -> usize {
     unsafe { cabocha_tree_sentence_size(self.inner) }
   }
 
-  pub fn set_sentence<T: Into<Vec<u8>>>(&mut self, sentence: T) {
+  pub fn set_sentence<T: Into<Vec<u8>>>(&mut self, sentence: T) -> Result<()> {
     let string = sentence.into();
     let len = string.len();
+    let input = str_to_heap_ptr(string)?;
     self.free_input();
-    self.input = str_to_heap_ptr(string);
+    self.input = input;
     unsafe {
       cabocha_tree_set_sentence(self.inner, self.input, len);
     }
+    Ok(())
   }
 
-  pub fn token(&self, index: usize) -> Option<Token> {
+  pub fn token(&self, index: usize) -> Option<Token<'_>> {
     if index < self.token_size() {
       let raw_ptr = unsafe { cabocha_tree_token(self.inner, index) };
       if raw_ptr.is_null() {
         None
       } else {
-        Some(Token::new(raw_ptr))
+        Some(Token::new(self, index, raw_ptr))
       }
     } else {
       None
     }
   }
 
-  pub fn chunk(&self, index: usize) -> Option<Chunk> {
+  pub fn chunk(&self, index: usize) -> Option<Chunk<'_>> {
     if index < self.chunk_size() {
       let raw_ptr = unsafe { cabocha_tree_chunk(self.inner, index) };
       if raw_ptr.is_null() {
         None
       } else {
-        Some(Chunk::new(raw_ptr))
+        Some(Chunk::new(self, index, raw_ptr))
       }
     } else {
       None
     }
   }
 
-  pub fn add_token(&self) -> Option<Token> {
+  pub fn add_token(&mut self) -> Option<Token<'_>> {
     let raw_ptr = unsafe { cabocha_tree_add_token(self.inner) };
     if raw_ptr.is_null() {
       None
     } else {
-      Some(Token::new(raw_ptr))
+      Some(Token::new(self, self.token_size() - 1, raw_ptr))
     }
   }
 
-  pub fn add_chunk(&self) -> Option<Chunk> {
+  pub fn add_chunk(&mut self) -> Option<Chunk<'_>> {
     let raw_ptr = unsafe { cabocha_tree_add_chunk(self.inner) };
     if raw_ptr.is_null() {
       None
     } else {
-      Some(Chunk::new(raw_ptr))
+      Some(Chunk::new(self, self.chunk_size() - 1, raw_ptr))
+    }
+  }
+
+  pub fn read(&mut self, input_layer: CABOCHA_INPUT) -> Result<()> {
+    if self.input.is_null() {
+      return Err(Error::Read(input_layer));
     }
+    let len = ptr_to_string(self.input)?.len();
+    if unsafe { cabocha_tree_read(self.inner, self.input, len, input_layer as i32) } == 0 {
+      return Err(Error::Read(input_layer));
+    }
+    Ok(())
   }
 
-  pub fn read(&self, input_layer: CABOCHA_INPUT) -> bool {
-    let len = ptr_to_string(self.input).len();
-    unsafe { cabocha_tree_read(self.inner, self.input, len, input_layer as i32) != 0 }
+  /// Rebuild a tree from cabocha's lattice output without a model.
+  /// The input is read as `DEP` when it has `* ` chunk lines and as `POS` otherwise.
+  /// Only one sentence is accepted; use `LatticeReader` for several.
+  pub fn from_lattice_str(lattice: &str) -> Result<Tree> {
+    let mut has_chunks = false;
+    let mut has_tokens = false;
+    let mut eos = false;
+    for (number, line) in lattice.lines().enumerate() {
+      if eos && !line.trim().is_empty() {
+        return Err(Error::InvalidInput(format!(
+          "line {}: data after EOS; use LatticeReader for several sentences",
+          number + 1
+        )));
+      }
+      if line == "EOS" {
+        eos = true;
+      } else if line.starts_with("* ") {
+        has_chunks = true;
+      } else if !line.trim().is_empty() {
+        has_tokens = true;
+      }
+    }
+    if !has_tokens {
+      return Err(Error::InvalidInput(String::from("lattice has no tokens")));
+    }
+    let input_layer = if has_chunks {
+      CABOCHA_INPUT::DEP
+    } else {
+      CABOCHA_INPUT::POS
+    };
+    let mut tree = Tree::new();
+    tree.set_charset(CABOCHA_CHARSET_TYPE::UTF8);
+    tree.read_str(lattice, input_layer)?;
+    Ok(tree)
+  }
+
+  /// Rebuild a tree from one sentence of `CABOCHA_FORMAT::CONLL` output,
+  /// see `TreeBuilder::from_conll`.
+  pub fn from_conll_str(conll: &str) -> Result<Tree> {
+    TreeBuilder::from_conll(conll)?.build()
+  }
+
+  /// Load cabocha lattice text at `input_layer`, as `read` does for a sentence
+  /// set with `set_sentence`. `Parser::parse_tree` then runs the layers above it.
+  pub fn read_str(&mut self, input: &str, input_layer: CABOCHA_INPUT) -> Result<()> {
+    let input = str_to_heap_ptr(input)?;
+    self.free_input();
+    self.input = input;
+    self.read(input_layer)
   }
 
   pub fn empty(&self) -> bool {
     unsafe { cabocha_tree_empty(self.inner) != 0 }
   }
 
-  pub fn clear(&self) {
+  pub fn clear(&mut self) {
     unsafe {
       cabocha_tree_clear(self.inner);
     }
     self.free_input();
   }
 
-  pub fn clear_chunk(&self) {
+  pub fn clear_chunk(&mut self) {
     unsafe { cabocha_tree_clear_chunk(self.inner) }
     self.free_input();
   }
@@ -146,8 +233,12 @@ impl Tree {
     unsafe { cabocha_tree_size(self.inner) }
   }
 
-  pub fn to_string(&self, format_type: CABOCHA_FORMAT) -> String {
-    unsafe { ptr_to_string(cabocha_tree_tostr(self.inner, format_type as c_int)) }
+  pub fn to_string(&self, format_type: CABOCHA_FORMAT) -> Result<String> {
+    let result = unsafe { cabocha_tree_tostr(self.inner, format_type as c_int) };
+    if result.is_null() {
+      return Err(Error::NullHandle);
+    }
+    ptr_to_string(result)
   }
 
   pub fn charset(&self) -> Option<CABOCHA_CHARSET_TYPE> {
@@ -194,19 +285,563 @@ impl Tree {
     unsafe { cabocha_tree_set_output_layer(self.inner, output_layer as c_int) }
   }
 
-  pub fn tokens(&self) -> Vec<Token> {
+  pub fn tokens(&self) -> Vec<Token<'_>> {
     self.token_iter().collect()
   }
 
-  pub fn chunks(&self) -> Vec<Chunk> {
+  pub fn chunks(&self) -> Vec<Chunk<'_>> {
     self.chunk_iter().collect()
   }
 
-  pub fn chunk_iter(&self) -> ChunkIter {
-    ChunkIter::new(&self)
+  pub fn chunk_iter(&self) -> ChunkIter<'_> {
+    ChunkIter::new(self)
+  }
+
+  pub fn token_iter(&self) -> TokenIter<'_> {
+    TokenIter::new(self)
+  }
+
+  pub fn root(&self) -> Option<Chunk<'_>> {
+    self.chunk_iter().find(|chunk| chunk.link() < 0)
+  }
+
+  pub fn path_between(&self, from: usize, to: usize) -> Option<Vec<Chunk<'_>>> {
+    let from = self.chunk(from)?;
+    let to = self.chunk(to)?;
+    let from_path = std::iter::once(from)
+      .chain(from.ancestors())
+      .collect::<Vec<_>>();
+    let to_path = std::iter::once(to)
+      .chain(to.ancestors())
+      .collect::<Vec<_>>();
+    for (i, chunk) in from_path.iter().enumerate() {
+      if let Some(j) = to_path.iter().position(|c| c.index() == chunk.index()) {
+        let mut path = from_path[..=i].to_vec();
+        path.extend(to_path[..j].iter().rev());
+        return Some(path);
+      }
+    }
+    None
+  }
+
+  /// Byte spans of all tokens, aligned in a single pass over the sentence.
+  /// Prefer this over calling `Token::byte_span` for every token.
+  pub fn token_byte_spans(&self) -> Result<Vec<Range<usize>>> {
+    self.token_byte_spans_until(self.token_size())
+  }
+
+  /// Char spans of all tokens; see `token_byte_spans`.
+  pub fn token_char_spans(&self) -> Result<Vec<Range<usize>>> {
+    let sentence = self.sentence_str()?;
+    Ok(to_char_spans(sentence, &self.token_byte_spans()?))
+  }
+
+  pub(crate) fn token_byte_spans_until(&self, end: usize) -> Result<Vec<Range<usize>>> {
+    let surfaces = self
+      .token_iter()
+      .take(end)
+      .map(|token| token.surface_str())
+      .collect::<Result<Vec<_>>>()?;
+    align_surfaces(self.sentence_str()?, &surfaces)
+  }
+
+  pub fn named_entities(&self) -> Result<Vec<NamedEntity>> {
+    let tokens = self.tokens();
+    let surfaces = tokens
+      .iter()
+      .map(|token| token.surface_str())
+      .collect::<Result<Vec<_>>>()?;
+    let tags = tokens
+      .iter()
+      .map(|token| token.ne_str())
+      .collect::<Result<Vec<_>>>()?;
+    named_entities(self.sentence_str()?, &surfaces, &tags)
+  }
+
+  /// Check the NE tags with `validate_iob`.
+  pub fn validate_ne(&self) -> Result<()> {
+    let tags = self
+      .tokens()
+      .iter()
+      .map(|token| token.ne_str())
+      .collect::<Result<Vec<_>>>()?;
+    validate_iob(&tags)
+  }
+
+  pub fn to_owned_tree(&self) -> Result<OwnedTree> {
+    OwnedTree::from_tree(self)
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct TreeBuilder {
+  tokens: Vec<(String, Vec<String>)>,
+  chunks: Option<Vec<usize>>,
+  heads: Option<Vec<(usize, usize)>>,
+  links: Option<Vec<i32>>,
+  charset: CABOCHA_CHARSET_TYPE,
+  posset: CABOCHA_POSSET_TYPE,
+}
+
+impl Default for TreeBuilder {
+  fn default() -> Self {
+    TreeBuilder {
+      tokens: Vec::new(),
+      chunks: None,
+      heads: None,
+      links: None,
+      charset: CABOCHA_CHARSET_TYPE::UTF8,
+      posset: CABOCHA_POSSET_TYPE::IPA,
+    }
+  }
+}
+
+impl TreeBuilder {
+  pub fn new() -> TreeBuilder {
+    TreeBuilder::default()
+  }
+
+  pub fn token<S, I, F>(mut self, surface: S, features: I) -> TreeBuilder
+  where
+    S: Into<String>,
+    I: IntoIterator<Item = F>,
+    F: Into<String>,
+  {
+    self.tokens.push((
+      surface.into(),
+      features.into_iter().map(Into::into).collect(),
+    ));
+    self
+  }
+
+  pub fn tokens<S, I, F, T>(self, tokens: T) -> TreeBuilder
+  where
+    S: Into<String>,
+    I: IntoIterator<Item = F>,
+    F: Into<String>,
+    T: IntoIterator<Item = (S, I)>,
+  {
+    tokens
+      .into_iter()
+      .fold(self, |builder, (surface, features)| {
+        builder.token(surface, features)
+      })
+  }
+
+  /// Chunk segmentation given as the number of tokens in each chunk.
+  pub fn chunks<I: IntoIterator<Item = usize>>(mut self, token_sizes: I) -> TreeBuilder {
+    self.chunks = Some(token_sizes.into_iter().collect());
+    self
+  }
+
+  /// Head and function word positions within each chunk.
+  /// When not given, the head is the last content word and the function word
+  /// the last particle or auxiliary after it, judged by `MorphFeatures::upos`.
+  pub fn heads<I: IntoIterator<Item = (usize, usize)>>(mut self, heads: I) -> TreeBuilder {
+    self.heads = Some(heads.into_iter().collect());
+    self
+  }
+
+  /// The chunk each chunk depends on, `-1` for the root.
+  pub fn links<I: IntoIterator<Item = i32>>(mut self, links: I) -> TreeBuilder {
+    self.links = Some(links.into_iter().collect());
+    self
+  }
+
+  pub fn charset(mut self, charset: CABOCHA_CHARSET_TYPE) -> TreeBuilder {
+    self.charset = charset;
+    self
+  }
+
+  pub fn posset(mut self, posset: CABOCHA_POSSET_TYPE) -> TreeBuilder {
+    self.posset = posset;
+    self
+  }
+
+  /// The layer the built tree starts from.
+  /// A `Parser` used on it should be built with the same `input_layer`.
+  pub fn input_layer(&self) -> CABOCHA_INPUT {
+    match (&self.chunks, &self.links) {
+      (Some(_), Some(_)) => CABOCHA_INPUT::DEP,
+      (Some(_), None) => CABOCHA_INPUT::CHUNK,
+      _ => CABOCHA_INPUT::POS,
+    }
+  }
+
+  fn is_functional(&self, index: usize) -> Option<bool> {
+    MorphFeatures::decode(self.posset, &self.tokens[index].1).is_functional()
+  }
+
+  fn derive_func(&self, tokens: Range<usize>, head: usize) -> usize {
+    derive_func(tokens, head, |index| self.is_functional(index))
+  }
+
+  fn derive_head(&self, tokens: Range<usize>) -> (usize, usize) {
+    let head = tokens
+      .clone()
+      .rev()
+      .find(|&index| self.is_functional(index) == Some(false))
+      .unwrap_or(tokens.start);
+    let func = self.derive_func(tokens.clone(), head);
+    (head - tokens.start, func - tokens.start)
+  }
+
+  /// Read one sentence of `CABOCHA_FORMAT::CONLL` output.
+  /// Chunks and links come from the HEAD and DEPREL columns and features from
+  /// FEATS; scores are lost and function words are derived as in `heads`.
+  pub fn from_conll(conll: &str) -> Result<TreeBuilder> {
+    let invalid = |number: usize, message: String| {
+      Error::InvalidInput(format!("line {}: {}", number + 1, message))
+    };
+    let mut builder = TreeBuilder::new();
+    // HEAD of every token as a 0-based index, `None` for the root, and DEPREL
+    let mut heads: Vec<(usize, Option<usize>, bool)> = Vec::new();
+    let mut has_heads = false;
+    let mut ended = false;
+    for (number, line) in conll.lines().enumerate() {
+      if line.trim().is_empty() {
+        ended = !heads.is_empty();
+        continue;
+      }
+      if ended {
+        return Err(invalid(number, String::from("data after the sentence")));
+      }
+      let fields = line.split('\t').collect::<Vec<_>>();
+      if fields.len() != 10 {
+        return Err(invalid(
+          number,
+          format!("expected 10 columns, found {}", fields.len()),
+        ));
+      }
+      if fields[0].parse::<usize>().ok() != Some(heads.len() + 1) {
+        return Err(invalid(
+          number,
+          format!("expected ID {}, found {:?}", heads.len() + 1, fields[0]),
+        ));
+      }
+      let features = match fields[5] {
+        "_" => Vec::new(),
+        features => features.split('|').collect(),
+      };
+      builder = builder.token(fields[1], features);
+      let head = match (fields[6], fields[7]) {
+        ("_", "_") => None,
+        (head, deprel @ ("D" | "_")) => match head.parse::<usize>() {
+          Ok(0) if deprel == "D" => Some(None),
+          Ok(head) if head > 0 && head != heads.len() + 1 => Some(Some(head - 1)),
+          _ => return Err(invalid(number, format!("bad HEAD {:?}", head))),
+        },
+        (_, deprel) => return Err(invalid(number, format!("bad DEPREL {:?}", deprel))),
+      };
+      if heads.is_empty() {
+        has_heads = head.is_some();
+      } else if has_heads != head.is_some() {
+        return Err(invalid(
+          number,
+          String::from("HEAD is given for some tokens only"),
+        ));
+      }
+      let (head, is_chunk_head) = match head {
+        Some(head) => (head, fields[7] == "D"),
+        None => (None, false),
+      };
+      heads.push((number, head, is_chunk_head));
+    }
+    if heads.is_empty() {
+      return Err(Error::InvalidInput(String::from(
+        "CoNLL input has no tokens",
+      )));
+    }
+    if has_heads {
+      // the head token of the chunk each token belongs to
+      let mut owners = Vec::with_capacity(heads.len());
+      for (index, &(number, head, is_chunk_head)) in heads.iter().enumerate() {
+        let owner = match head {
+          _ if is_chunk_head => index,
+          Some(head) if head < heads.len() && heads[head].2 => head,
+          _ => {
+            return Err(invalid(
+              number,
+              String::from("HEAD is not the head token of a chunk"),
+            ))
+          }
+        };
+        if let Some(&last) = owners.last() {
+          if owner != last && owners.contains(&owner) {
+            return Err(invalid(number, String::from("chunk is not contiguous")));
+          }
+        }
+        owners.push(owner);
+      }
+      let mut starts: Vec<usize> = Vec::new();
+      for (index, owner) in owners.iter().enumerate() {
+        if index == 0 || owners[index - 1] != *owner {
+          starts.push(index);
+        }
+      }
+      let chunk_of = |token: usize| starts.iter().rposition(|&start| start <= token).unwrap();
+      let mut links = Vec::with_capacity(starts.len());
+      let mut chunk_heads = Vec::with_capacity(starts.len());
+      for (id, &start) in starts.iter().enumerate() {
+        let end = starts.get(id + 1).copied().unwrap_or(heads.len());
+        let head = owners[start];
+        let (number, link, _) = heads[head];
+        links.push(match link {
+          None => -1,
+          Some(link) if link < heads.len() && heads[link].2 => chunk_of(link) as i32,
+          Some(_) => {
+            return Err(invalid(
+              number,
+              String::from("HEAD is not the head token of a chunk"),
+            ))
+          }
+        });
+        let func = builder.derive_func(start..end, head);
+        chunk_heads.push((head - start, func - start));
+      }
+      let sizes = starts
+        .iter()
+        .zip(starts.iter().skip(1).chain(Some(&heads.len())))
+        .map(|(start, end)| end - start)
+        .collect::<Vec<_>>();
+      builder = builder.chunks(sizes).links(links).heads(chunk_heads);
+    }
+    Ok(builder)
+  }
+
+  pub fn to_lattice(&self) -> Result<String> {
+    if self.chunks.is_none() && (self.links.is_some() || self.heads.is_some()) {
+      return Err(Error::InvalidInput(String::from(
+        "links or heads are given without chunks",
+      )));
+    }
+    let chunks = self.chunks.clone().unwrap_or_default();
+    if self.chunks.is_some() && chunks.iter().sum::<usize>() != self.tokens.len() {
+      return Err(Error::InvalidInput(format!(
+        "chunks cover {} tokens, but {} tokens are given",
+        chunks.iter().sum::<usize>(),
+        self.tokens.len()
+      )));
+    }
+    if let Some(index) = chunks.iter().position(|&size| size == 0) {
+      return Err(Error::InvalidInput(format!("chunk {} is empty", index)));
+    }
+    if let Some(links) = &self.links {
+      if links.len() != chunks.len() {
+        return Err(Error::InvalidInput(format!(
+          "{} links are given for {} chunks",
+          links.len(),
+          chunks.len()
+        )));
+      }
+      if let Some(link) = links
+        .iter()
+        .find(|&&link| link < -1 || link >= chunks.len() as i32)
+      {
+        return Err(Error::InvalidInput(format!(
+          "link {} is out of range",
+          link
+        )));
+      }
+    }
+    if let Some(heads) = &self.heads {
+      if heads.len() != chunks.len() {
+        return Err(Error::InvalidInput(format!(
+          "{} heads are given for {} chunks",
+          heads.len(),
+          chunks.len()
+        )));
+      }
+      if let Some(index) = heads
+        .iter()
+        .zip(chunks.iter())
+        .position(|(&(head, func), &size)| head >= size || func >= size)
+      {
+        return Err(Error::InvalidInput(format!(
+          "head or function word of chunk {} is out of range",
+          index
+        )));
+      }
+    }
+
+    let mut lattice = String::new();
+    let mut id = 0;
+    let mut chunk_start = 0;
+    for (index, (surface, features)) in self.tokens.iter().enumerate() {
+      if id < chunks.len() && index == chunk_start {
+        let (head, func) = match &self.heads {
+          Some(heads) => heads[id],
+          None => self.derive_head(chunk_start..chunk_start + chunks[id]),
+        };
+        let link = self.links.as_ref().map_or(-1, |links| links[id]);
+        writeln!(lattice, "* {} {}D {}/{} 0.000000", id, link, head, func).unwrap();
+        chunk_start += chunks[id];
+        id += 1;
+      }
+      let feature = features.join(",");
+      if surface.is_empty()
+        || [surface, &feature]
+          .iter()
+          .any(|s| s.contains(&['\t', '\n'][..]))
+      {
+        return Err(Error::InvalidInput(format!(
+          "token {} has an empty surface or contains a tab or newline",
+          index
+        )));
+      }
+      writeln!(lattice, "{}\t{}", surface, feature).unwrap();
+    }
+    lattice.push_str("EOS\n");
+    Ok(lattice)
+  }
+
+  pub fn build(&self) -> Result<Tree> {
+    let lattice = self.to_lattice()?;
+    let mut tree = Tree::new();
+    tree.set_charset(self.charset);
+    tree.set_posset(self.posset);
+    tree.read_str(&lattice, self.input_layer())?;
+    Ok(tree)
+  }
+
+  /// Build the tree and run the remaining layers with `parser`.
+  pub fn parse(&self, parser: &mut Parser) -> Result<Tree> {
+    let mut tree = self.build()?;
+    parser.parse_tree(&mut tree)?;
+    Ok(tree)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn builder() -> TreeBuilder {
+    TreeBuilder::new().tokens(vec![
+      (
+        "一郎",
+        "名詞,固有名詞,人名,名,*,*,一郎,イチロウ,イチロー".split(','),
+      ),
+      ("は", "助詞,係助詞,*,*,*,*,は,ハ,ワ".split(',')),
+      (
+        "贈っ",
+        "動詞,自立,*,*,五段・ラ行,連用タ接続,贈る,オクッ,オクッ".split(','),
+      ),
+      ("た", "助動詞,*,*,*,特殊・タ,基本形,た,タ,タ".split(',')),
+      ("。", "記号,句点,*,*,*,*,。,。,。".split(',')),
+    ])
+  }
+
+  #[test]
+  fn test_tree_builder_to_lattice() {
+    let builder = builder();
+    assert_eq!(CABOCHA_INPUT::POS, builder.input_layer());
+    assert_eq!(
+      "一郎\t名詞,固有名詞,人名,名,*,*,一郎,イチロウ,イチロー
+は\t助詞,係助詞,*,*,*,*,は,ハ,ワ
+贈っ\t動詞,自立,*,*,五段・ラ行,連用タ接続,贈る,オクッ,オクッ
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ
+。\t記号,句点,*,*,*,*,。,。,。
+EOS
+",
+      builder.to_lattice().unwrap()
+    );
+
+    let builder = builder.chunks(vec![2, 3]).links(vec![1, -1]);
+    assert_eq!(CABOCHA_INPUT::DEP, builder.input_layer());
+    let lattice = builder.to_lattice().unwrap();
+    assert!(lattice.starts_with("* 0 1D 0/1 0.000000\n一郎\t"));
+    assert!(lattice.contains("\n* 1 -1D 0/1 0.000000\n贈っ\t"));
+
+    let lattice = builder.heads(vec![(0, 0), (1, 2)]).to_lattice().unwrap();
+    assert!(lattice.starts_with("* 0 1D 0/0 0.000000\n一郎\t"));
+    assert!(lattice.contains("\n* 1 -1D 1/2 0.000000\n贈っ\t"));
+  }
+
+  #[test]
+  fn test_tree_builder_invalid_input() {
+    let result = builder().chunks(vec![2, 2]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder().chunks(vec![2, 3]).links(vec![1]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder().chunks(vec![2, 3]).links(vec![2, -1]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder().links(vec![-1]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder().token("a\tb", vec!["名詞"]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder().chunks(vec![0, 5]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder().chunks(vec![]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder()
+      .chunks(vec![2, 3])
+      .heads(vec![(0, 2), (0, 1)])
+      .to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    let result = builder().heads(vec![(0, 0)]).to_lattice();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+  }
+
+  #[test]
+  fn test_tree_builder_parse() {
+    let mut parser = Parser::builder()
+      .input_layer(CABOCHA_INPUT::POS)
+      .build()
+      .unwrap();
+    let tree = builder().parse(&mut parser).unwrap();
+    assert_eq!(5, tree.token_size());
+    assert_eq!(2, tree.chunk_size());
+    assert_eq!(1, tree.chunk(0).unwrap().link());
+    assert_eq!("一郎は贈った。", tree.sentence_str().unwrap());
+
+    let mut parser = Parser::builder()
+      .input_layer(CABOCHA_INPUT::CHUNK)
+      .build()
+      .unwrap();
+    let tree = builder().chunks(vec![2, 3]).parse(&mut parser).unwrap();
+    assert_eq!(2, tree.chunk_size());
+    assert_eq!(1, tree.chunk(0).unwrap().link());
   }
 
-  pub fn token_iter(&self) -> TokenIter {
-    TokenIter::new(&self)
+  #[test]
+  fn test_tree_builder_build_dep() {
+    let tree = builder()
+      .chunks(vec![2, 3])
+      .links(vec![1, -1])
+      .build()
+      .unwrap();
+    assert_eq!(2, tree.chunk_size());
+    assert_eq!(1, tree.chunk(0).unwrap().link());
+    assert_eq!(-1, tree.chunk(1).unwrap().link());
+    assert_eq!(2, tree.chunk(1).unwrap().token_pos());
+    assert_eq!(
+      "贈っ",
+      tree
+        .chunk(1)
+        .unwrap()
+        .head_token()
+        .unwrap()
+        .surface_str()
+        .unwrap()
+    );
+    assert_eq!(
+      "た",
+      tree
+        .chunk(1)
+        .unwrap()
+        .func_token()
+        .unwrap()
+        .surface_str()
+        .unwrap()
+    );
   }
 }
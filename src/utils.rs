@@ -1,22 +1,27 @@
 use std::ffi::{CStr, CString};
+use std::ops::Range;
 use std::os::raw::*;
 use std::{slice, str};
 
-pub fn ptr_to_string(ptr: *const c_char) -> String {
-  let strs = if ptr.is_null() {
-    ""
+use super::error::*;
+
+pub fn ptr_to_str<'a>(ptr: *const c_char) -> Result<&'a str> {
+  if ptr.is_null() {
+    Ok("")
   } else {
     let cstr = unsafe { CStr::from_ptr(ptr) };
-    if let Ok(s) = str::from_utf8(cstr.to_bytes()) {
-      s
-    } else {
-      ""
-    }
-  };
-  strs.to_string()
+    Ok(str::from_utf8(cstr.to_bytes())?)
+  }
+}
+
+pub fn ptr_to_string(ptr: *const c_char) -> Result<String> {
+  ptr_to_str(ptr).map(str::to_string)
 }
 
-pub fn ptr_to_vec_string(ptr: *const *const c_char, size: usize) -> Vec<String> {
+pub fn ptr_to_vec_string(ptr: *const *const c_char, size: usize) -> Result<Vec<String>> {
+  if ptr.is_null() {
+    return Ok(Vec::new());
+  }
   let slice = unsafe { slice::from_raw_parts(ptr, size) };
   slice
     .iter()
@@ -24,8 +29,55 @@ pub fn ptr_to_vec_string(ptr: *const *const c_char, size: usize) -> Vec<String>
     .collect()
 }
 
-pub fn str_to_heap_ptr<T: Into<Vec<u8>>>(input: T) -> *mut c_char {
-  CString::new(input).unwrap().into_raw()
+pub fn str_to_heap_ptr<T: Into<Vec<u8>>>(input: T) -> Result<*mut c_char> {
+  Ok(CString::new(input)?.into_raw())
+}
+
+pub fn align_surfaces<S: AsRef<str>>(sentence: &str, surfaces: &[S]) -> Result<Vec<Range<usize>>> {
+  let mut spans = Vec::with_capacity(surfaces.len());
+  let mut cursor = 0;
+  for (index, surface) in surfaces.iter().enumerate() {
+    let surface = surface.as_ref();
+    let rest = &sentence[cursor..];
+    let skipped = rest.len() - rest.trim_start().len();
+    let start = if rest[skipped..].starts_with(surface) {
+      cursor + skipped
+    } else {
+      rest
+        .find(surface)
+        .map(|pos| cursor + pos)
+        .ok_or(Error::Alignment(index))?
+    };
+    cursor = start + surface.len();
+    spans.push(start..cursor);
+  }
+  Ok(spans)
+}
+
+pub fn byte_to_char_offsets(sentence: &str) -> Vec<usize> {
+  let mut offsets = vec![0; sentence.len() + 1];
+  let mut chars = 0;
+  for (pos, ch) in sentence.char_indices() {
+    for offset in offsets.iter_mut().skip(pos).take(ch.len_utf8()) {
+      *offset = chars;
+    }
+    chars += 1;
+  }
+  offsets[sentence.len()] = chars;
+  offsets
+}
+
+pub fn to_char_spans(sentence: &str, spans: &[Range<usize>]) -> Vec<Range<usize>> {
+  let offsets = byte_to_char_offsets(sentence);
+  spans
+    .iter()
+    .map(|span| offsets[span.start]..offsets[span.end])
+    .collect()
+}
+
+pub fn to_char_span(sentence: &str, span: &Range<usize>) -> Range<usize> {
+  let start = sentence[..span.start].chars().count();
+  start..start + sentence[span.clone()].chars().count()
 }
 
 #[cfg(test)]
@@ -45,18 +97,73 @@ mod tests {
       .collect::<Vec<*const c_char>>();
     vec_ptr.push(std::ptr::null());
     let ptr: *const *const c_char = vec_ptr.as_ptr();
-    let result = ptr_to_vec_string(ptr, vec_string.len());
+    let result = ptr_to_vec_string(ptr, vec_string.len()).unwrap();
     assert_eq!(vec_string, result);
   }
 
   #[test]
   fn test_str_to_heap_ptr_and_ptr_to_string() {
     let string = "string";
-    let ptr = str_to_heap_ptr(string);
-    let result = ptr_to_string(ptr);
+    let ptr = str_to_heap_ptr(string).unwrap();
+    let result = ptr_to_string(ptr).unwrap();
     assert_eq!(string, result);
     unsafe {
-      CString::from_raw(ptr);
+      drop(CString::from_raw(ptr));
     }
   }
+
+  #[test]
+  fn test_str_to_heap_ptr_interior_nul() {
+    let result = str_to_heap_ptr("str\0ing");
+    assert!(matches!(result, Err(Error::InteriorNul(_))));
+  }
+
+  #[test]
+  fn test_ptr_to_string_invalid_encoding() {
+    let cstring = CString::new(vec![0xff, 0xfe]).unwrap();
+    let result = ptr_to_string(cstring.as_ptr());
+    assert!(matches!(result, Err(Error::InvalidEncoding(_))));
+  }
+
+  #[test]
+  fn test_ptr_to_str() {
+    let cstring = CString::new("string").unwrap();
+    assert_eq!("string", ptr_to_str(cstring.as_ptr()).unwrap());
+    assert_eq!("", ptr_to_str(std::ptr::null()).unwrap());
+  }
+
+  #[test]
+  fn test_align_surfaces() {
+    let spans = align_surfaces(
+      "一郎は 二郎が\t描いた",
+      &["一郎", "は", "二郎", "が", "描い", "た"],
+    )
+    .unwrap();
+    assert_eq!(vec![0..6, 6..9, 10..16, 16..19, 20..26, 26..29], spans);
+
+    let result = align_surfaces("一郎は", &["一郎", "が"]);
+    assert!(matches!(result, Err(Error::Alignment(1))));
+  }
+
+  #[test]
+  fn test_byte_to_char_offsets() {
+    let offsets = byte_to_char_offsets("a一b");
+    assert_eq!(vec![0, 1, 1, 1, 2, 3], offsets);
+  }
+
+  #[test]
+  fn test_to_char_spans() {
+    let spans = to_char_spans("a 一b", &[0..1, 2..5, 5..6]);
+    assert_eq!(vec![0..1, 2..3, 3..4], spans);
+  }
+
+  #[test]
+  fn test_to_char_span() {
+    assert_eq!(2..4, to_char_span("a 一b", &(2..6)));
+  }
+
+  #[test]
+  fn test_ptr_to_string_null() {
+    assert_eq!("", ptr_to_string(std::ptr::null()).unwrap());
+  }
 }
@@ -0,0 +1,361 @@
+use std::io::Write;
+
+use super::consts::*;
+use super::error::*;
+use super::owned::*;
+use super::xml::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+  /// Digits after the decimal point of chunk scores; cabocha prints 6.
+  pub score_precision: usize,
+}
+
+impl Default for WriteOptions {
+  fn default() -> Self {
+    WriteOptions { score_precision: 6 }
+  }
+}
+
+/// Display width used by cabocha's tree art: one column for ASCII, two otherwise.
+pub(crate) fn width(text: &str) -> usize {
+  text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+impl OwnedTree {
+  pub fn write<W: Write>(&self, writer: &mut W, format: CABOCHA_FORMAT) -> Result<()> {
+    self.write_with(writer, format, &WriteOptions::default())
+  }
+
+  /// Render the tree like `Tree::to_string` without the C library.
+  pub fn write_with<W: Write>(
+    &self,
+    writer: &mut W,
+    format: CABOCHA_FORMAT,
+    options: &WriteOptions,
+  ) -> Result<()> {
+    if self.output_layer == Some(CABOCHA_OUTPUT::RAW_SENTENCE) {
+      if format != CABOCHA_FORMAT::NONE {
+        writeln!(writer, "{}", self.sentence)?;
+      }
+      return Ok(());
+    }
+    match format {
+      CABOCHA_FORMAT::TREE => self.write_tree(writer),
+      CABOCHA_FORMAT::LATTICE => self.write_lattice(writer, options),
+      CABOCHA_FORMAT::TREE_LATTICE => {
+        self.write_tree(writer)?;
+        self.write_lattice(writer, options)
+      }
+      CABOCHA_FORMAT::XML => self.write_xml(
+        writer,
+        &XmlOptions {
+          write: *options,
+          chunk_features: self.with_features(),
+          ..XmlOptions::default()
+        },
+      ),
+      CABOCHA_FORMAT::CONLL => self.write_conll(writer),
+      CABOCHA_FORMAT::NONE => Ok(()),
+    }
+  }
+
+  pub fn to_string_with(&self, format: CABOCHA_FORMAT, options: &WriteOptions) -> Result<String> {
+    let mut output = Vec::new();
+    self.write_with(&mut output, format, options)?;
+    Ok(String::from_utf8(output).map_err(|err| err.utf8_error())?)
+  }
+
+  fn chunk_surface(&self, index: usize) -> String {
+    self
+      .chunk_tokens(index)
+      .iter()
+      .map(|token| token.surface.as_str())
+      .collect()
+  }
+
+  /// The chunk starting at each token, if any.
+  pub(crate) fn chunk_starts(&self) -> Vec<Option<usize>> {
+    let mut starts = vec![None; self.tokens.len()];
+    for (index, chunk) in self.chunks.iter().enumerate() {
+      if chunk.token_size > 0 {
+        if let Some(start) = starts.get_mut(chunk.token_pos) {
+          *start = Some(index);
+        }
+      }
+    }
+    starts
+  }
+
+  pub(crate) fn with_features(&self) -> bool {
+    self.output_layer == Some(CABOCHA_OUTPUT::SELECTION)
+  }
+
+  fn write_tree<W: Write>(&self, writer: &mut W) -> Result<()> {
+    let surfaces = (0..self.chunk_size())
+      .map(|index| self.chunk_surface(index))
+      .collect::<Vec<_>>();
+    let widths = surfaces
+      .iter()
+      .map(|surface| width(surface))
+      .collect::<Vec<_>>();
+    let max_width = widths.iter().copied().max().unwrap_or(0);
+    let mut linked = vec![false; surfaces.len()];
+    for (i, surface) in surfaces.iter().enumerate() {
+      let link = self.chunks[i].link;
+      let mut line = " ".repeat(2 * i + max_width - widths[i]);
+      line.push_str(surface);
+      let mut drawn = false;
+      for (j, linked) in linked.iter_mut().enumerate().skip(i + 1) {
+        if link == j as i32 {
+          line.push_str("-D");
+          drawn = true;
+          *linked = true;
+        } else if *linked {
+          line.push_str(" |");
+        } else if drawn {
+          line.push_str("  ");
+        } else {
+          line.push_str("--");
+        }
+      }
+      writeln!(writer, "{}", line)?;
+    }
+    writeln!(writer, "EOS")?;
+    Ok(())
+  }
+
+  fn write_lattice<W: Write>(&self, writer: &mut W, options: &WriteOptions) -> Result<()> {
+    let starts = self.chunk_starts();
+    for (i, token) in self.tokens.iter().enumerate() {
+      if let Some(index) = starts[i] {
+        let chunk = &self.chunks[index];
+        write!(
+          writer,
+          "* {} {}D {}/{} {:.*}",
+          index,
+          chunk.link,
+          chunk.head_pos,
+          chunk.func_pos,
+          options.score_precision,
+          f64::from(chunk.score)
+        )?;
+        if self.with_features() {
+          for feature in chunk.feature_list.iter() {
+            write!(writer, " {}", feature)?;
+          }
+        }
+        writeln!(writer)?;
+      }
+      write!(writer, "{}\t{}", token.surface, token.feature)?;
+      if !token.ne.is_empty() {
+        write!(writer, "\t{}", token.ne)?;
+      }
+      writeln!(writer)?;
+    }
+    writeln!(writer, "EOS")?;
+    Ok(())
+  }
+
+  /// CoNLL-X: one line per token and a blank line after the sentence.
+  /// The head token of each chunk depends on the head token of the chunk it
+  /// links to with `D` (HEAD `0` for the root), and the other tokens depend on
+  /// the head token of their own chunk with `_`. HEAD and DEPREL are `_` without
+  /// chunks. FEATS holds the whole feature list with `|` for `,`.
+  fn write_conll<W: Write>(&self, writer: &mut W) -> Result<()> {
+    let posset = self.posset.unwrap_or(CABOCHA_POSSET_TYPE::IPA);
+    let head_id = |index: usize| {
+      let chunk = &self.chunks[index];
+      chunk.token_pos + chunk.head_pos.min(chunk.token_size.max(1) - 1) + 1
+    };
+    for (i, token) in self.tokens.iter().enumerate() {
+      let morph = token.morph_features(posset);
+      let mut pos = vec![morph.pos.as_str()];
+      pos.extend(morph.pos_details());
+      let (head, deprel) = match token.chunk.filter(|&index| index < self.chunks.len()) {
+        Some(index) if head_id(index) == i + 1 => {
+          let link = self.chunks[index].link;
+          if link < 0 || link as usize >= self.chunks.len() {
+            (String::from("0"), "D")
+          } else {
+            (head_id(link as usize).to_string(), "D")
+          }
+        }
+        Some(index) => (head_id(index).to_string(), "_"),
+        None => (String::from("_"), "_"),
+      };
+      writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t_\t_",
+        i + 1,
+        token.surface,
+        morph.base_form.as_deref().unwrap_or("_"),
+        if morph.pos.is_empty() {
+          "_"
+        } else {
+          &morph.pos
+        },
+        if morph.pos.is_empty() {
+          String::from("_")
+        } else {
+          pos.join("-")
+        },
+        if token.feature.is_empty() {
+          String::from("_")
+        } else {
+          token.feature.replace(',', "|")
+        },
+        head,
+        deprel
+      )?;
+    }
+    writeln!(writer)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::*;
+  use crate::parser::Parser;
+
+  const TREE: &str = "  一郎は---------D
+    二郎が-D     |
+      描いた-D   |
+          絵を---D
+          三郎に-D
+          贈った。
+EOS
+";
+
+  const LATTICE: &str = "* 0 5D 0/1 -1.663431
+一郎\t名詞,固有名詞,人名,名,*,*,一郎,イチロウ,イチロー
+は\t助詞,係助詞,*,*,*,*,は,ハ,ワ
+* 1 2D 0/1 0.916559
+二郎\t名詞,固有名詞,人名,名,*,*,二郎,ジロウ,ジロー
+が\t助詞,格助詞,一般,*,*,*,が,ガ,ガ
+* 2 3D 0/1 1.678682
+描い\t動詞,自立,*,*,五段・カ行イ音便,連用タ接続,描く,エガイ,エガイ
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ
+* 3 5D 0/1 -1.663431
+絵\t名詞,一般,*,*,*,*,絵,エ,エ
+を\t助詞,格助詞,一般,*,*,*,を,ヲ,ヲ
+* 4 5D 0/1 -1.663431
+三郎\t名詞,固有名詞,地域,一般,*,*,三郎,サブロウ,サブロー
+に\t助詞,格助詞,一般,*,*,*,に,ニ,ニ
+* 5 -1D 0/1 0.000000
+贈っ\t動詞,自立,*,*,五段・ラ行,連用タ接続,贈る,オクッ,オクッ
+た\t助動詞,*,*,*,特殊・タ,基本形,た,タ,タ
+。\t記号,句点,*,*,*,*,。,。,。
+EOS
+";
+
+  fn render(tree: &OwnedTree, format: CABOCHA_FORMAT) -> String {
+    tree
+      .to_string_with(format, &WriteOptions::default())
+      .unwrap()
+  }
+
+  #[test]
+  fn test_write_tree() {
+    assert_eq!(TREE, render(&from_lattice(LATTICE), CABOCHA_FORMAT::TREE));
+  }
+
+  #[test]
+  fn test_write_lattice() {
+    let tree = from_lattice(LATTICE);
+    assert_eq!(LATTICE, render(&tree, CABOCHA_FORMAT::LATTICE));
+    assert_eq!(
+      format!("{}{}", TREE, LATTICE),
+      render(&tree, CABOCHA_FORMAT::TREE_LATTICE)
+    );
+    assert_eq!("", render(&tree, CABOCHA_FORMAT::NONE));
+  }
+
+  #[test]
+  fn test_write_score_precision() {
+    let tree = from_lattice(LATTICE);
+    let lattice = tree
+      .to_string_with(
+        CABOCHA_FORMAT::LATTICE,
+        &WriteOptions { score_precision: 2 },
+      )
+      .unwrap();
+    assert!(lattice.starts_with("* 0 5D 0/1 -1.66\n"));
+    assert!(lattice.contains("* 5 -1D 0/1 0.00\n"));
+  }
+
+  #[test]
+  fn test_write_xml() {
+    let mut tree = from_lattice(
+      "* 0 1D 0/1 1.000000
+A&B\t名詞,固有名詞,組織,*,*,*,*
+は\t助詞,係助詞,*,*,*,*,は,ハ,ワ
+* 1 -1D 0/0 0.000000
+\"<\"\t記号,一般,*,*,*,*,*\tO
+EOS
+",
+    );
+    tree.tokens[0].ne = String::from("B-ORGANIZATION");
+    assert_eq!(
+      "<sentence>
+ <chunk id=\"0\" link=\"1\" rel=\"D\" score=\"1.000000\" head=\"0\" func=\"1\">
+  <tok id=\"0\" feature=\"名詞,固有名詞,組織,*,*,*,*\" ne=\"B-ORGANIZATION\">A&amp;B</tok>
+  <tok id=\"1\" feature=\"助詞,係助詞,*,*,*,*,は,ハ,ワ\">は</tok>
+ </chunk>
+ <chunk id=\"1\" link=\"-1\" rel=\"D\" score=\"0.000000\" head=\"2\" func=\"2\">
+  <tok id=\"2\" feature=\"記号,一般,*,*,*,*,*\" ne=\"O\">&quot;&lt;&quot;</tok>
+ </chunk>
+</sentence>
+",
+      render(&tree, CABOCHA_FORMAT::XML)
+    );
+  }
+
+  #[test]
+  fn test_write_conll() {
+    let tree = from_lattice(LATTICE);
+    let conll = render(&tree, CABOCHA_FORMAT::CONLL);
+    let lines = conll.lines().collect::<Vec<_>>();
+    assert_eq!(14, lines.len());
+    assert_eq!(
+      "1\t一郎\t一郎\t名詞\t名詞-固有名詞-人名-名\t名詞|固有名詞|人名|名|*|*|一郎|イチロウ|イチロー\t11\tD\t_\t_",
+      lines[0]
+    );
+    assert_eq!(
+      "2\tは\tは\t助詞\t助詞-係助詞\t助詞|係助詞|*|*|*|*|は|ハ|ワ\t1\t_\t_\t_",
+      lines[1]
+    );
+    assert!(lines[10].starts_with("11\t贈っ\t贈る\t動詞\t動詞-自立\t"));
+    assert!(lines[10].ends_with("\t0\tD\t_\t_"));
+    assert!(lines[12].ends_with("\t11\t_\t_\t_"));
+    assert_eq!("", lines[13]);
+
+    let mut tree = tree;
+    tree.chunks.clear();
+    tree.tokens.iter_mut().for_each(|token| token.chunk = None);
+    let conll = render(&tree, CABOCHA_FORMAT::CONLL);
+    assert!(conll.starts_with("1\t一郎\t一郎\t名詞\t名詞-固有名詞-人名-名\t名詞|固有名詞|人名|名|*|*|一郎|イチロウ|イチロー\t_\t_\t_\t_\n"));
+  }
+
+  #[test]
+  fn test_write_matches_tree_to_string() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    let owned = tree.to_owned_tree().unwrap();
+    for format in [
+      CABOCHA_FORMAT::TREE,
+      CABOCHA_FORMAT::LATTICE,
+      CABOCHA_FORMAT::TREE_LATTICE,
+      CABOCHA_FORMAT::XML,
+      CABOCHA_FORMAT::CONLL,
+    ]
+    .iter()
+    {
+      assert_eq!(tree.to_string(*format).unwrap(), render(&owned, *format));
+    }
+  }
+}
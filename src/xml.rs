@@ -0,0 +1,580 @@
+use std::io::{BufRead, Write};
+
+use super::consts::*;
+use super::error::*;
+use super::owned::*;
+use super::writer::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XmlOptions {
+  /// Options shared with `OwnedTree::write_with`, such as the score precision.
+  pub write: WriteOptions,
+  /// Write the chunk `feature` attribute; cabocha does so only at the SELECTION layer.
+  pub chunk_features: bool,
+  /// Write the token `ne` attribute for tokens with an NE tag.
+  pub ne: bool,
+}
+
+impl Default for XmlOptions {
+  fn default() -> Self {
+    XmlOptions {
+      write: WriteOptions::default(),
+      chunk_features: false,
+      ne: true,
+    }
+  }
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      _ => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn unescape_xml(text: &str) -> Result<String> {
+  let mut unescaped = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(start) = rest.find('&') {
+    unescaped.push_str(&rest[..start]);
+    let end = rest[start..]
+      .find(';')
+      .map(|end| start + end)
+      .ok_or_else(|| Error::InvalidInput(format!("unterminated entity in {:?}", text)))?;
+    let entity = &rest[start + 1..end];
+    let c = match entity {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      _ => match entity.strip_prefix('#') {
+        Some(code) => match code.strip_prefix('x').or_else(|| code.strip_prefix('X')) {
+          Some(hex) => u32::from_str_radix(hex, 16).ok(),
+          None => code.parse::<u32>().ok(),
+        }
+        .and_then(std::char::from_u32),
+        None => None,
+      },
+    };
+    unescaped.push(c.ok_or_else(|| Error::InvalidInput(format!("unknown entity &{};", entity)))?);
+    rest = &rest[end + 1..];
+  }
+  unescaped.push_str(rest);
+  Ok(unescaped)
+}
+
+enum Event<'a> {
+  Start(&'a str, Vec<(&'a str, String)>),
+  End(&'a str),
+  Text(String),
+}
+
+/// Just enough XML for cabocha output: elements, attributes, text and entities.
+/// Declarations, comments and doctypes are skipped.
+struct Lexer<'a> {
+  input: &'a str,
+  pos: usize,
+  pending_end: Option<&'a str>,
+}
+
+impl<'a> Lexer<'a> {
+  fn new(input: &'a str) -> Lexer<'a> {
+    Lexer {
+      input,
+      pos: 0,
+      pending_end: None,
+    }
+  }
+
+  fn rest(&self) -> &'a str {
+    &self.input[self.pos..]
+  }
+
+  fn skip_past(&mut self, end: &str) -> Result<()> {
+    match self.rest().find(end) {
+      Some(pos) => {
+        self.pos += pos + end.len();
+        Ok(())
+      }
+      None => Err(Error::InvalidInput(format!("missing {:?}", end))),
+    }
+  }
+
+  fn skip_whitespace(&mut self) {
+    let rest = self.rest();
+    self.pos += rest.len() - rest.trim_start().len();
+  }
+
+  fn name(&mut self) -> &'a str {
+    let rest = self.rest();
+    let len = rest
+      .find(|c: char| c.is_whitespace() || c == '/' || c == '>' || c == '=')
+      .unwrap_or(rest.len());
+    self.pos += len;
+    &rest[..len]
+  }
+
+  fn next(&mut self) -> Result<Option<Event<'a>>> {
+    if let Some(name) = self.pending_end.take() {
+      return Ok(Some(Event::End(name)));
+    }
+    loop {
+      let rest = self.rest();
+      if rest.is_empty() {
+        return Ok(None);
+      }
+      if rest.starts_with("<!--") {
+        self.skip_past("-->")?;
+      } else if rest.starts_with("<?") {
+        self.skip_past("?>")?;
+      } else if rest.starts_with("<!") {
+        self.skip_past(">")?;
+      } else if rest.starts_with("</") {
+        self.pos += 2;
+        let name = self.name();
+        self.skip_past(">")?;
+        return Ok(Some(Event::End(name)));
+      } else if rest.starts_with('<') {
+        self.pos += 1;
+        return self.start().map(Some);
+      } else {
+        let len = rest.find('<').unwrap_or(rest.len());
+        self.pos += len;
+        return Ok(Some(Event::Text(unescape_xml(&rest[..len])?)));
+      }
+    }
+  }
+
+  fn start(&mut self) -> Result<Event<'a>> {
+    let name = self.name();
+    let mut attributes = Vec::new();
+    loop {
+      self.skip_whitespace();
+      let rest = self.rest();
+      if rest.starts_with("/>") {
+        self.pos += 2;
+        self.pending_end = Some(name);
+        return Ok(Event::Start(name, attributes));
+      } else if rest.starts_with('>') {
+        self.pos += 1;
+        return Ok(Event::Start(name, attributes));
+      } else if rest.is_empty() {
+        return Err(Error::InvalidInput(format!("unterminated <{}>", name)));
+      }
+      let attribute = self.name();
+      self.skip_whitespace();
+      if !self.rest().starts_with('=') {
+        return Err(Error::InvalidInput(format!(
+          "attribute {} of <{}> has no value",
+          attribute, name
+        )));
+      }
+      self.pos += 1;
+      self.skip_whitespace();
+      let quote = match self.rest().chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => quote,
+        _ => {
+          return Err(Error::InvalidInput(format!(
+            "attribute {} of <{}> is not quoted",
+            attribute, name
+          )))
+        }
+      };
+      self.pos += 1;
+      let rest = self.rest();
+      let len = rest
+        .find(quote)
+        .ok_or_else(|| Error::InvalidInput(format!("unterminated <{}>", name)))?;
+      self.pos += len + 1;
+      attributes.push((attribute, unescape_xml(&rest[..len])?));
+    }
+  }
+}
+
+fn attribute<'a>(attributes: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+  attributes
+    .iter()
+    .find(|(key, _)| *key == name)
+    .map(|(_, value)| value.as_str())
+}
+
+fn parse_attribute<T: std::str::FromStr>(
+  attributes: &[(&str, String)],
+  name: &str,
+  default: T,
+) -> Result<T> {
+  match attribute(attributes, name) {
+    Some(value) => value
+      .trim()
+      .parse()
+      .map_err(|_| Error::InvalidInput(format!("bad {} attribute {:?}", name, value))),
+    None => Ok(default),
+  }
+}
+
+fn split_features(features: &str) -> Vec<String> {
+  if features.is_empty() {
+    Vec::new()
+  } else {
+    features.split(',').map(String::from).collect()
+  }
+}
+
+/// Read the first `<sentence>` element, ignoring anything around it.
+fn read_sentence(input: &str) -> Result<Option<OwnedTree>> {
+  let mut lexer = Lexer::new(input);
+  let mut in_sentence = false;
+  let mut chunks: Vec<OwnedChunk> = Vec::new();
+  let mut tokens: Vec<OwnedToken> = Vec::new();
+  let mut token: Option<OwnedToken> = None;
+  let mut chunk_features = false;
+  while let Some(event) = lexer.next()? {
+    match event {
+      Event::Start("sentence", _) => in_sentence = true,
+      _ if !in_sentence => {}
+      Event::Start("chunk", attributes) => {
+        let token_pos = tokens.len();
+        let relative = |name| {
+          let index = parse_attribute(&attributes, name, token_pos)?;
+          index
+            .checked_sub(token_pos)
+            .ok_or_else(|| Error::InvalidInput(format!("{} {} precedes its chunk", name, index)))
+        };
+        let feature_list = match attribute(&attributes, "feature") {
+          Some(features) => {
+            chunk_features = true;
+            split_features(features)
+          }
+          None => Vec::new(),
+        };
+        chunks.push(OwnedChunk {
+          link: parse_attribute(&attributes, "link", -1)?,
+          head_pos: relative("head")?,
+          func_pos: relative("func")?,
+          token_size: 0,
+          token_pos,
+          score: parse_attribute(&attributes, "score", 0.0)?,
+          feature_list,
+          additional_info: String::new(),
+        });
+      }
+      Event::End("chunk") => {
+        if let Some(chunk) = chunks.last_mut() {
+          chunk.token_size = tokens.len() - chunk.token_pos;
+        }
+      }
+      Event::Start("tok", attributes) => {
+        let feature = attribute(&attributes, "feature").unwrap_or_default();
+        token = Some(OwnedToken {
+          surface: String::new(),
+          normalized_surface: String::new(),
+          feature: feature.to_string(),
+          feature_list: split_features(feature),
+          ne: attribute(&attributes, "ne").unwrap_or_default().to_string(),
+          additional_info: String::new(),
+          chunk: chunks.len().checked_sub(1),
+        });
+      }
+      Event::Text(text) => {
+        if let Some(token) = token.as_mut() {
+          token.surface.push_str(&text);
+        }
+      }
+      Event::End("tok") => {
+        if let Some(mut token) = token.take() {
+          token.normalized_surface = token.surface.clone();
+          tokens.push(token);
+        }
+      }
+      Event::End("sentence") => {
+        let output_layer = if chunk_features {
+          CABOCHA_OUTPUT::SELECTION
+        } else if chunks.is_empty() {
+          CABOCHA_OUTPUT::POS
+        } else {
+          CABOCHA_OUTPUT::DEP
+        };
+        return Ok(Some(OwnedTree {
+          sentence: tokens.iter().map(|token| token.surface.as_str()).collect(),
+          charset: None,
+          posset: None,
+          output_layer: Some(output_layer),
+          chunks,
+          tokens,
+        }));
+      }
+      _ => {}
+    }
+  }
+  if in_sentence {
+    Err(Error::InvalidInput(String::from(
+      "unexpected end of input in <sentence>",
+    )))
+  } else {
+    Ok(None)
+  }
+}
+
+impl OwnedTree {
+  /// Write `<sentence><chunk><tok>` XML in the layout of `CABOCHA_FORMAT::XML`.
+  pub fn write_xml<W: Write>(&self, writer: &mut W, options: &XmlOptions) -> Result<()> {
+    let starts = self.chunk_starts();
+    writeln!(writer, "<sentence>")?;
+    let mut open = false;
+    for (i, token) in self.tokens.iter().enumerate() {
+      if let Some(index) = starts[i] {
+        let chunk = &self.chunks[index];
+        if open {
+          writeln!(writer, " </chunk>")?;
+        }
+        write!(
+          writer,
+          " <chunk id=\"{}\" link=\"{}\" rel=\"D\" score=\"{:.*}\" head=\"{}\" func=\"{}\"",
+          index,
+          chunk.link,
+          options.write.score_precision,
+          f64::from(chunk.score),
+          chunk.token_pos + chunk.head_pos,
+          chunk.token_pos + chunk.func_pos
+        )?;
+        if options.chunk_features {
+          write!(
+            writer,
+            " feature=\"{}\"",
+            escape_xml(&chunk.feature_list.join(","))
+          )?;
+        }
+        writeln!(writer, ">")?;
+        open = true;
+      }
+      write!(
+        writer,
+        "  <tok id=\"{}\" feature=\"{}\"",
+        i,
+        escape_xml(&token.feature)
+      )?;
+      if options.ne && !token.ne.is_empty() {
+        write!(writer, " ne=\"{}\"", escape_xml(&token.ne))?;
+      }
+      writeln!(writer, ">{}</tok>", escape_xml(&token.surface))?;
+    }
+    if open {
+      writeln!(writer, " </chunk>")?;
+    }
+    writeln!(writer, "</sentence>")?;
+    Ok(())
+  }
+
+  /// Read the first `<sentence>` of cabocha XML.
+  ///
+  /// Chunks and tokens are taken in document order, so `id` attributes are not checked.
+  /// The sentence is the concatenation of the token surfaces.
+  pub fn from_xml(input: &str) -> Result<OwnedTree> {
+    read_sentence(input)?.ok_or_else(|| Error::InvalidInput(String::from("no <sentence> element")))
+  }
+}
+
+/// Iterates over the `<sentence>` elements of a cabocha XML stream,
+/// such as the output of `cabocha -f3` over many sentences.
+pub struct XmlReader<R> {
+  reader: R,
+  buffer: String,
+  done: bool,
+}
+
+impl<R: BufRead> XmlReader<R> {
+  pub fn new(reader: R) -> XmlReader<R> {
+    XmlReader {
+      reader,
+      buffer: String::new(),
+      done: false,
+    }
+  }
+}
+
+impl<R: BufRead> Iterator for XmlReader<R> {
+  type Item = Result<OwnedTree>;
+
+  fn next(&mut self) -> Option<Result<OwnedTree>> {
+    const END: &str = "</sentence>";
+    loop {
+      if let Some(pos) = self.buffer.find(END) {
+        let block = self.buffer.drain(..pos + END.len()).collect::<String>();
+        return read_sentence(&block).transpose();
+      }
+      if self.done {
+        let rest = std::mem::take(&mut self.buffer);
+        return read_sentence(&rest).transpose();
+      }
+      match self.reader.read_line(&mut self.buffer) {
+        Ok(0) => self.done = true,
+        Ok(_) => {}
+        Err(err) => {
+          self.done = true;
+          self.buffer.clear();
+          return Some(Err(err.into()));
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Parser;
+
+  const XML: &str = "<sentence>
+ <chunk id=\"0\" link=\"1\" rel=\"D\" score=\"1.250000\" head=\"0\" func=\"1\">
+  <tok id=\"0\" feature=\"名詞,固有名詞,組織,*,*,*,*\" ne=\"B-ORGANIZATION\">A&amp;B</tok>
+  <tok id=\"1\" feature=\"助詞,係助詞,*,*,*,*,は,ハ,ワ\">は</tok>
+ </chunk>
+ <chunk id=\"1\" link=\"-1\" rel=\"D\" score=\"0.000000\" head=\"3\" func=\"3\">
+  <tok id=\"2\" feature=\"記号,括弧開,*,*,*,*,&quot;,&quot;,&quot;\">&quot;</tok>
+  <tok id=\"3\" feature=\"記号,一般,*,*,*,*,*\" ne=\"O\">&lt;&gt;</tok>
+ </chunk>
+</sentence>
+";
+
+  fn write(tree: &OwnedTree, options: &XmlOptions) -> String {
+    let mut output = Vec::new();
+    tree.write_xml(&mut output, options).unwrap();
+    String::from_utf8(output).unwrap()
+  }
+
+  #[test]
+  fn test_unescape_xml() {
+    assert_eq!(
+      "<a & 'b'> \"あ\"",
+      unescape_xml("&lt;a &amp; &apos;b&apos;&gt; &quot;&#12354;&#x22;").unwrap()
+    );
+    assert!(matches!(
+      unescape_xml("&nbsp;"),
+      Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(unescape_xml("a & b"), Err(Error::InvalidInput(_))));
+  }
+
+  #[test]
+  fn test_from_xml() {
+    let tree = OwnedTree::from_xml(XML).unwrap();
+    assert_eq!("A&Bは\"<>", tree.sentence);
+    assert_eq!(Some(CABOCHA_OUTPUT::DEP), tree.output_layer);
+    assert_eq!(2, tree.chunk_size());
+    assert_eq!(1, tree.chunks[0].link);
+    assert_eq!(1.25, tree.chunks[0].score);
+    assert_eq!(1, tree.chunks[0].func_pos);
+    assert_eq!(2, tree.chunks[1].token_pos);
+    assert_eq!(2, tree.chunks[1].token_size);
+    assert_eq!(1, tree.chunks[1].head_pos);
+    assert_eq!("A&B", tree.tokens[0].surface);
+    assert_eq!("B-ORGANIZATION", tree.tokens[0].ne);
+    assert_eq!("", tree.tokens[1].ne);
+    assert_eq!("\"", tree.tokens[2].feature_list[6]);
+    assert_eq!(Some(1), tree.tokens[3].chunk);
+  }
+
+  #[test]
+  fn test_xml_round_trip() {
+    let tree = OwnedTree::from_xml(XML).unwrap();
+    assert_eq!(XML, write(&tree, &XmlOptions::default()));
+    assert_eq!(
+      tree,
+      OwnedTree::from_xml(&write(&tree, &XmlOptions::default())).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_write_xml_options() {
+    let mut tree = OwnedTree::from_xml(XML).unwrap();
+    tree.chunks[0].feature_list = vec![String::from("A:<x>"), String::from("FEOS:1")];
+    let xml = write(
+      &tree,
+      &XmlOptions {
+        write: WriteOptions { score_precision: 1 },
+        chunk_features: true,
+        ne: false,
+      },
+    );
+    assert!(xml.contains(" score=\"1.2\" head=\"0\" func=\"1\" feature=\"A:&lt;x&gt;,FEOS:1\">\n"));
+    assert!(!xml.contains(" ne="));
+
+    let read = OwnedTree::from_xml(&xml).unwrap();
+    assert_eq!(Some(CABOCHA_OUTPUT::SELECTION), read.output_layer);
+    assert_eq!(tree.chunks[0].feature_list, read.chunks[0].feature_list);
+    assert!(read.chunks[1].feature_list.is_empty());
+  }
+
+  #[test]
+  fn test_from_xml_lenient_syntax() {
+    let tree = OwnedTree::from_xml(
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!-- cabocha -f3 -->
+<sentence><chunk link = '-1' head='0' func='0'><tok feature='名詞'>猫</tok><tok/></chunk></sentence>",
+    )
+    .unwrap();
+    assert_eq!("猫", tree.sentence);
+    assert_eq!(2, tree.token_size());
+    assert_eq!(2, tree.chunks[0].token_size);
+    assert!(tree.tokens[1].feature_list.is_empty());
+  }
+
+  #[test]
+  fn test_from_xml_malformed() {
+    let invalid = |input: &str| matches!(OwnedTree::from_xml(input), Err(Error::InvalidInput(_)));
+    assert!(invalid(""));
+    assert!(invalid("<sentence><tok>猫</tok>"));
+    assert!(invalid("<sentence><chunk link=\"x\"></chunk></sentence>"));
+    assert!(invalid("<sentence><chunk link=-1></chunk></sentence>"));
+    assert!(invalid("<sentence><tok>&bogus;</tok></sentence>"));
+    assert!(invalid(
+      "<sentence><tok>a</tok><chunk head=\"0\"></chunk></sentence>"
+    ));
+  }
+
+  #[test]
+  fn test_xml_reader() {
+    let input = format!(
+      "<document>\n{}{}</document>\n",
+      XML,
+      XML.replace("A&amp;B", "C")
+    );
+    let trees = XmlReader::new(input.as_bytes())
+      .collect::<Result<Vec<_>>>()
+      .unwrap();
+    assert_eq!(2, trees.len());
+    assert_eq!("A&B", trees[0].tokens[0].surface);
+    assert_eq!("C", trees[1].tokens[0].surface);
+
+    let mut reader = XmlReader::new(&XML.as_bytes()[..XML.len() - 12]);
+    assert!(matches!(reader.next(), Some(Err(Error::InvalidInput(_)))));
+    assert!(reader.next().is_none());
+  }
+
+  #[test]
+  fn test_from_xml_matches_parser_output() {
+    let mut parser = Parser::new("").unwrap();
+    let tree = parser
+      .parse_to_tree("一郎は二郎が描いた絵を三郎に贈った。")
+      .unwrap();
+    let owned = tree.to_owned_tree().unwrap();
+    let read = OwnedTree::from_xml(&tree.to_string(CABOCHA_FORMAT::XML).unwrap()).unwrap();
+    assert_eq!(owned.sentence, read.sentence);
+    assert_eq!(owned.tokens, read.tokens);
+    for (expected, chunk) in owned.chunks.iter().zip(read.chunks.iter()) {
+      assert_eq!(expected.link, chunk.link);
+      assert_eq!(expected.head_pos, chunk.head_pos);
+      assert_eq!(expected.func_pos, chunk.func_pos);
+      assert!((expected.score - chunk.score).abs() < 1.0e-6);
+    }
+  }
+}